@@ -0,0 +1,81 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+build.rs - Generating the C header & pkg-config file of Dokkoo's libraries
+
+With the `capi` feature, the declarations of the C API in `src/ffi.rs` are generated as `dokkoo.h`, alongside the `dokkoo.pc` file letting C programs find the installed libraries with pkg-config.
+Both are written to Cargo's output folder; if they differ from the copies in the repository (`include/dokkoo.h` & `dokkoo.pc`), which are the ones installed, a warning says which to replace.
+*/
+
+fn main() {
+	println!("cargo:rerun-if-changed=build.rs");
+	#[cfg(feature = "capi")]
+	generate_c_api();
+}
+
+/// Generates the C header & pkg-config file, warning if the copies in the repository are out of date
+#[cfg(feature = "capi")]
+fn generate_c_api() {
+	use std::env;
+	use std::fs;
+
+	println!("cargo:rerun-if-changed=src/ffi.rs");
+	println!("cargo:rerun-if-changed=cbindgen.toml");
+	println!("cargo:rerun-if-changed=include/dokkoo.h");
+	println!("cargo:rerun-if-changed=dokkoo.pc");
+	let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+	let out_dir = env::var("OUT_DIR").unwrap();
+
+	let config = cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml")).unwrap();
+	let header_path = format!("{out_dir}/dokkoo.h");
+	cbindgen::Builder::new()
+		.with_config(config)
+		.with_src(format!("{crate_dir}/src/ffi.rs"))
+		.generate()
+		.expect("Unable to generate the C header of Dokkoo")
+		.write_to_file(&header_path);
+
+	let pkg_config = format!(
+		"prefix=/usr
+exec_prefix=${{prefix}}
+libdir=${{exec_prefix}}/lib
+includedir=${{prefix}}/include
+
+Name: dokkoo
+Description: {}
+Version: {}
+URL: {}
+Libs: -L${{libdir}} -ldokkoo
+Libs.private: -lpthread -ldl -lm
+Cflags: -I${{includedir}}
+",
+		env::var("CARGO_PKG_DESCRIPTION").unwrap(),
+		env::var("CARGO_PKG_VERSION").unwrap(),
+		env::var("CARGO_PKG_REPOSITORY").unwrap()
+	);
+	let pkg_config_path = format!("{out_dir}/dokkoo.pc");
+	fs::write(&pkg_config_path, pkg_config).unwrap();
+
+	for (generated, committed) in [
+		(header_path, "include/dokkoo.h"),
+		(pkg_config_path, "dokkoo.pc"),
+	] {
+		if fs::read(&generated).ok() != fs::read(format!("{crate_dir}/{committed}")).ok() {
+			println!("cargo:warning=`{committed}` is out of date; replace it with `{generated}`.");
+		}
+	}
+}
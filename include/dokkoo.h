@@ -0,0 +1,99 @@
+/* dokkoo.h - The C API of Dokkoo, a Mokk (Macro Output Key Kit) implementation. Licensed under the GNU Affero General Public License, version 3 or later. */
+
+#ifndef DOKKOO_H
+#define DOKKOO_H
+
+/* This file is generated by cbindgen from src/ffi.rs when Dokkoo is built with the `capi` feature. Do not edit it by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+typedef struct DokkooBuild DokkooBuild;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates a build of the Mokk at the given path. Returns `NULL` on failure.
+ *
+ * # Arguments
+ *
+ * * `path` - Path to a Mokk, or `NULL` to use the current directory
+ *
+ * # Safety
+ *
+ * `path` must be `NULL` or point to a valid nul-terminated string
+ */
+DokkooBuild *dokkoo_build_new(const char *path);
+
+/**
+ * Frees a build
+ *
+ * # Arguments
+ *
+ * * `build` - A build created by `dokkoo_build_new`, or `NULL`
+ *
+ * # Safety
+ *
+ * `build` must be `NULL` or a build created by `dokkoo_build_new` that has not already been freed
+ */
+void dokkoo_build_free(DokkooBuild *build);
+
+/**
+ * Renders text as the contents of a page, returning the HTML, or `NULL` on failure. The HTML is freed with `dokkoo_string_free`.
+ *
+ * # Arguments
+ *
+ * * `build` - A build created by `dokkoo_build_new`
+ *
+ * * `text` - The text to render, which may use Liquid
+ *
+ * * `format` - The markup format of the text, such as `markdown`, `html` or `org`; `NULL` for Markdown
+ *
+ * # Safety
+ *
+ * `build` must be a build created by `dokkoo_build_new`, and `text` & `format` must be `NULL` or point to valid nul-terminated strings
+ */
+char *dokkoo_render(DokkooBuild *build,
+                    const char *text,
+                    const char *format);
+
+/**
+ * Builds the Mokk at the given path into its output folder. Returns `0` on success, or `-1` on failure, including when any page could not be compiled.
+ *
+ * # Arguments
+ *
+ * * `path` - Path to a Mokk
+ *
+ * # Safety
+ *
+ * `path` must point to a valid nul-terminated string
+ */
+int dokkoo_build_directory(const char *path);
+
+/**
+ * Returns the message of the last error to occur on this thread, or `NULL` if none has. The message belongs to Dokkoo, and is valid until the next error on this thread.
+ */
+const char *dokkoo_last_error(void);
+
+/**
+ * Frees a string returned by Dokkoo
+ *
+ * # Arguments
+ *
+ * * `string` - A string returned by Dokkoo, or `NULL`
+ *
+ * # Safety
+ *
+ * `string` must be `NULL` or a string returned by Dokkoo that has not already been freed
+ */
+void dokkoo_string_free(char *string);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* DOKKOO_H */
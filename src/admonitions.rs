@@ -0,0 +1,271 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+admonitions.rs - Admonitions (callouts) in Markdown
+
+Admonitions are written either as GitHub-style blockquotes (`> [!NOTE]`) or as fenced containers (`:::tip`), and are rendered as `<aside>` elements.
+*/
+
+use crate::escape_html;
+use crate::scan::replace_outside_code_blocks;
+use ahash::AHashMap;
+use comrak::arena_tree::Node;
+use comrak::nodes::{Ast, AstNode, LineColumn, NodeHtmlBlock, NodeValue};
+use comrak::Arena;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+/// Configuration of admonitions, from the `admonitions` key of the global file
+pub struct Admonitions {
+	/// The CSS class given to every admonition
+	pub class: String,
+	/// The CSS class of admonitions' titles
+	pub title_class: String,
+	/// The CSS class given to each kind of admonition, in addition to `class`; defaults to the kind in lowercase
+	pub classes: AHashMap<String, String>,
+	/// The title of each kind of admonition when one is not given; defaults to the kind, capitalised. Empty titles are not rendered.
+	pub titles: AHashMap<String, String>,
+}
+
+/// The initial state of an `Admonitions` object
+impl Default for Admonitions {
+	fn default() -> Self {
+		Self {
+			class: "admonition".to_owned(),
+			title_class: "admonition-title".to_owned(),
+			classes: AHashMap::new(),
+			titles: AHashMap::new(),
+		}
+	}
+}
+
+impl Admonitions {
+	/// Returns the HTML opening an admonition
+	///
+	/// # Arguments
+	///
+	/// * `kind` - The kind of admonition, such as `note` or `warning`
+	///
+	/// * `title` - The title given to the admonition, if any
+	pub fn opening_html(&self, kind: &str, title: Option<&str>) -> String {
+		let kind = kind.to_lowercase();
+		let kind_class = self.classes.get(&kind).unwrap_or(&kind);
+		let title = match title.map(str::trim).filter(|t| !t.is_empty()) {
+			Some(title) => title.to_owned(),
+			None => match self.titles.get(&kind) {
+				Some(title) => title.to_owned(),
+				None => capitalise(&kind),
+			},
+		};
+		let mut html = format!(
+			"<aside class=\"{} {}\">\n",
+			escape_html(&self.class),
+			escape_html(kind_class)
+		);
+		if !title.is_empty() {
+			html.push_str(&format!(
+				"<p class=\"{}\">{}</p>\n",
+				escape_html(&self.title_class),
+				escape_html(&title)
+			));
+		}
+		html
+	}
+
+	/// Returns the HTML closing an admonition
+	pub fn closing_html(&self) -> &'static str {
+		"</aside>\n"
+	}
+}
+
+/// Returns text with its first letter in uppercase
+///
+/// # Arguments
+///
+/// * `text` - The text to capitalise
+fn capitalise(text: &str) -> String {
+	let mut chars = text.chars();
+	match chars.next() {
+		Some(first) => first.to_uppercase().chain(chars).collect(),
+		None => String::new(),
+	}
+}
+
+/// Returns the kind & title of an admonition, if a line opens a fenced container (e.g. `:::tip Title`), or `None` for the kind if it closes one (`:::`)
+///
+/// # Arguments
+///
+/// * `line` - A line of a document
+fn parse_container_fence(line: &str) -> Option<Option<(&str, &str)>> {
+	let trimmed = line.trim();
+	let rest = trimmed.trim_start_matches(':');
+	if trimmed.len() - rest.len() < 3 {
+		return None;
+	}
+	let rest = rest.trim_start();
+	if rest.is_empty() {
+		return Some(None);
+	}
+	let kind_length = rest
+		.find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+		.unwrap_or(rest.len());
+	match kind_length {
+		0 => None,
+		_ => Some(Some((&rest[..kind_length], &rest[kind_length..]))),
+	}
+}
+
+/// Replaces fenced containers (`:::tip` … `:::`) in a Markdown document with the HTML of admonitions
+///
+/// # Arguments
+///
+/// * `text` - The text of the Markdown document
+///
+/// * `options` - The configuration of admonitions
+pub fn replace_containers(text: &str, options: &Admonitions) -> String {
+	let mut open_containers = 0;
+	let mut output = replace_outside_code_blocks(text, |prose| {
+		let mut output = String::with_capacity(prose.len());
+		for line in prose.split_inclusive('\n') {
+			// Blank lines surround the HTML, so that the container's contents are rendered as Markdown
+			match parse_container_fence(line) {
+				Some(Some((kind, title))) => {
+					output.push('\n');
+					output.push_str(&options.opening_html(kind, Some(title)));
+					output.push('\n');
+					open_containers += 1;
+				}
+				Some(None) if open_containers > 0 => {
+					output.push('\n');
+					output.push_str(options.closing_html());
+					output.push('\n');
+					open_containers -= 1;
+				}
+				_ => output.push_str(line),
+			}
+		}
+		output
+	});
+	for _ in 0..open_containers {
+		output.push('\n');
+		output.push_str(options.closing_html());
+	}
+	output
+}
+
+/// Returns the text within an inline node, without any formatting
+///
+/// # Arguments
+///
+/// * `node` - An inline node
+fn plain_text<'a>(node: &'a AstNode<'a>) -> String {
+	node.descendants()
+		.filter_map(|descendant| match &descendant.data.borrow().value {
+			NodeValue::Text(text) => Some(text.to_owned()),
+			NodeValue::Code(code) => Some(code.literal.to_owned()),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Returns a new HTML block node
+///
+/// # Arguments
+///
+/// * `arena` - The arena holding the document's nodes
+///
+/// * `html` - The contents of the HTML block
+fn html_block<'a>(arena: &'a Arena<AstNode<'a>>, html: String) -> &'a AstNode<'a> {
+	arena.alloc(Node::new(RefCell::new(Ast::new(
+		NodeValue::HtmlBlock(NodeHtmlBlock {
+			block_type: 6,
+			literal: html,
+		}),
+		LineColumn { line: 0, column: 0 },
+	))))
+}
+
+/// Replaces blockquotes beginning with `[!KIND]` (e.g. `> [!NOTE]`) with admonitions.
+/// Text following the marker on its line is used as the admonition's title.
+///
+/// # Arguments
+///
+/// * `arena` - The arena holding the document's nodes
+///
+/// * `root` - The root node of the Markdown document
+///
+/// * `options` - The configuration of admonitions
+pub fn replace_blockquotes<'a>(
+	arena: &'a Arena<AstNode<'a>>,
+	root: &'a AstNode<'a>,
+	options: &Admonitions,
+) {
+	let blockquotes: Vec<&'a AstNode<'a>> = root
+		.descendants()
+		.filter(|node| matches!(node.data.borrow().value, NodeValue::BlockQuote))
+		.collect();
+	for blockquote in blockquotes {
+		let Some(paragraph) = blockquote
+			.first_child()
+			.filter(|child| matches!(child.data.borrow().value, NodeValue::Paragraph))
+		else {
+			continue;
+		};
+
+		// The marker is on the first line of the blockquote's first paragraph
+		let mut first_line = Vec::new();
+		let mut line_break = None;
+		for child in paragraph.children() {
+			match child.data.borrow().value {
+				NodeValue::SoftBreak | NodeValue::LineBreak => {
+					line_break = Some(child);
+					break;
+				}
+				_ => first_line.push(child),
+			}
+		}
+		let text: String = first_line.iter().map(|node| plain_text(node)).collect();
+		let Some(marker) = text.trim_start().strip_prefix("[!") else {
+			continue;
+		};
+		let Some((kind, title)) = marker.split_once(']') else {
+			continue;
+		};
+		if kind.is_empty()
+			|| !kind
+				.chars()
+				.all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+		{
+			continue;
+		}
+
+		for node in first_line.into_iter().chain(line_break) {
+			node.detach();
+		}
+		if paragraph.first_child().is_none() {
+			paragraph.detach();
+		}
+		blockquote.insert_before(html_block(arena, options.opening_html(kind, Some(title))));
+		while let Some(child) = blockquote.first_child() {
+			blockquote.insert_before(child);
+		}
+		blockquote.insert_before(html_block(arena, options.closing_html().to_owned()));
+		blockquote.detach();
+	}
+}
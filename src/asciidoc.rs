@@ -0,0 +1,337 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+asciidoc.rs - AsciiDoc
+
+Mokk files written in AsciiDoc (`format: asciidoc`) are rendered with a lightweight subset of its syntax: section titles, lists, tables, delimited blocks (listings, literals, quotes, examples, sidebars & passthroughs), admonitions, and inline markup & links.
+Document attributes (`:name: value`) & comments are not rendered; a page's metadata belongs in its frontmatter.
+*/
+
+use crate::admonitions::Admonitions;
+use crate::escape_html;
+use crate::formats::{
+	format_inline, plain_text, render_table, split_table_row, BlockWriter, ContentRenderer,
+	InlineSyntax,
+};
+use crate::highlight::{CodeBlockAttributes, Highlighter};
+use crate::toc::HeadingCollector;
+use std::sync::Arc;
+
+/// The kinds of admonition in AsciiDoc
+const ADMONITION_KINDS: [&str; 5] = ["NOTE", "TIP", "IMPORTANT", "WARNING", "CAUTION"];
+
+/// The inline syntax of AsciiDoc
+pub const ASCIIDOC_INLINE: InlineSyntax = InlineSyntax {
+	delimiters: &[
+		('*', "strong", false),
+		('_', "em", false),
+		('#', "mark", false),
+		('`', "code", true),
+	],
+	link: asciidoc_link,
+};
+
+/// Renders an AsciiDoc link at the start of some text: a URL (`https://example.com[label]`), a link macro (`link:path[label]`), or a cross reference (`<<id,label>>`)
+///
+/// # Arguments
+///
+/// * `text` - The text, possibly beginning with a link
+///
+/// * `syntax` - The inline syntax of AsciiDoc, used to render the link's label
+fn asciidoc_link(text: &str, syntax: &InlineSyntax) -> Option<(String, usize)> {
+	if let Some(inner) = text.strip_prefix("<<") {
+		let end = inner.find(">>")?;
+		let (id, label) = match inner[..end].split_once(',') {
+			Some((id, label)) => (id.trim(), format_inline(label.trim(), syntax)),
+			None => (inner[..end].trim(), escape_html(inner[..end].trim())),
+		};
+		return Some((
+			format!("<a href=\"#{}\">{label}</a>", escape_html(id)),
+			end + 4,
+		));
+	}
+
+	let (prefix, rest) = match text.strip_prefix("link:") {
+		Some(rest) => ("link:".len(), rest),
+		None if ["https://", "http://", "mailto:"]
+			.iter()
+			.any(|scheme| text.starts_with(scheme)) =>
+		{
+			(0, text)
+		}
+		None => return None,
+	};
+	let target_end = rest
+		.find(|c: char| c.is_whitespace() || c == '[')
+		.unwrap_or(rest.len());
+	let target = &rest[..target_end];
+	if target.is_empty() {
+		return None;
+	}
+	match rest[target_end..].strip_prefix('[') {
+		Some(label) => {
+			let label_end = label.find(']')?;
+			let label_html = match label[..label_end].trim() {
+				"" => escape_html(target),
+				label => format_inline(label, syntax),
+			};
+			Some((
+				format!("<a href=\"{}\">{label_html}</a>", escape_html(target)),
+				prefix + target_end + label_end + 2,
+			))
+		}
+		// Bare URLs end before any trailing punctuation
+		None if prefix == 0 => {
+			let target = target.trim_end_matches(['.', ',', ';', ':', '!', '?', ')']);
+			Some((
+				format!(
+					"<a href=\"{}\">{}</a>",
+					escape_html(target),
+					escape_html(target)
+				),
+				target.len(),
+			))
+		}
+		None => None,
+	}
+}
+
+/// Returns the list element & text of an AsciiDoc list item, if a line is one
+///
+/// # Arguments
+///
+/// * `line` - A line of an AsciiDoc document, without its indentation
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+	let marker = line.chars().next()?;
+	let depth = line.len() - line.trim_start_matches(marker).len();
+	let item = line[depth..].strip_prefix(' ')?;
+	match (marker, depth) {
+		('*', _) | ('-', 1) => Some(("ul", item)),
+		('.', _) => Some(("ol", item)),
+		_ => None,
+	}
+}
+
+/// Returns whether a line delimits a block, being four or more of the given character
+///
+/// # Arguments
+///
+/// * `line` - A line of an AsciiDoc document
+///
+/// * `delimiter` - The character the block is delimited by
+fn is_delimiter(line: &str, delimiter: char) -> bool {
+	line.len() >= 4 && line.chars().all(|c| c == delimiter)
+}
+
+/// Returns whether a line is a document attribute entry, such as `:toc:` or `:author: Name`
+///
+/// # Arguments
+///
+/// * `line` - A line of an AsciiDoc document
+fn is_attribute_entry(line: &str) -> bool {
+	line.strip_prefix(':')
+		.and_then(|rest| rest.split_once(':'))
+		.is_some_and(|(name, value)| {
+			!name.is_empty()
+				&& name
+					.chars()
+					.all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '!')
+				&& (value.is_empty() || value.starts_with(' '))
+		})
+}
+
+/// Renders Mokk files written in AsciiDoc
+pub struct AsciiDocRenderer {
+	/// The syntax highlighter, used for source listings
+	pub highlighter: Arc<Highlighter>,
+	/// The configuration of admonitions
+	pub admonitions: Admonitions,
+}
+
+impl ContentRenderer for AsciiDocRenderer {
+	fn render(&self, text: &str, heading_collector: &HeadingCollector, _math: bool) -> String {
+		let lines: Vec<&str> = text.lines().collect();
+		let mut writer = BlockWriter::default();
+		// The attribute list (`[source,rust]`) & title (`.main.rs`) given to the next block
+		let mut attributes: Vec<String> = Vec::new();
+		let mut title: Option<String> = None;
+		let mut i = 0;
+		while i < lines.len() {
+			let line = lines[i];
+			let trimmed = line.trim();
+			i += 1;
+
+			if trimmed.is_empty() {
+				writer.finish_blocks(&ASCIIDOC_INLINE);
+				continue;
+			}
+
+			// Delimited blocks, from one delimiter line to a matching one
+			if let Some(delimiter) = ['-', '.', '_', '=', '*', '+', '/']
+				.into_iter()
+				.find(|d| is_delimiter(trimmed, *d))
+			{
+				let mut body = String::new();
+				while i < lines.len() && lines[i].trim() != trimmed {
+					body.push_str(lines[i]);
+					body.push('\n');
+					i += 1;
+				}
+				i += 1;
+				let style = attributes.first().map(String::as_str).unwrap_or_default();
+				let html = match delimiter {
+					'-' => {
+						let lang = match style {
+							"source" => attributes.get(1).map(String::as_str),
+							_ => None,
+						};
+						let mut code_attributes =
+							CodeBlockAttributes::parse("", self.highlighter.options.line_numbers);
+						code_attributes.title = title.take();
+						if attributes.iter().any(|a| a == "linenums") {
+							code_attributes.line_numbers = true;
+						}
+						self.highlighter
+							.render_code_block(&body, lang, &code_attributes)
+					}
+					'.' => format!("<pre>{}</pre>\n", escape_html(&body)),
+					'_' => format!(
+						"<blockquote>\n{}</blockquote>\n",
+						self.render(&body, heading_collector, false)
+					),
+					'=' if ADMONITION_KINDS.contains(&style) => format!(
+						"{}{}{}",
+						self.admonitions
+							.opening_html(style, title.take().as_deref()),
+						self.render(&body, heading_collector, false),
+						self.admonitions.closing_html()
+					),
+					'=' => format!(
+						"<div class=\"example\">\n{}</div>\n",
+						self.render(&body, heading_collector, false)
+					),
+					'*' => format!(
+						"<aside class=\"sidebar\">\n{}</aside>\n",
+						self.render(&body, heading_collector, false)
+					),
+					'+' => body,
+					_ => String::new(),
+				};
+				attributes.clear();
+				title = None;
+				writer.push_html(&html, &ASCIIDOC_INLINE);
+				continue;
+			}
+
+			// Comments, document attributes, block attributes & block titles
+			if trimmed.starts_with("//") {
+				continue;
+			}
+			if is_attribute_entry(trimmed) {
+				continue;
+			}
+			if trimmed.starts_with('[') && trimmed.ends_with(']') && !trimmed.starts_with("[[") {
+				writer.finish_blocks(&ASCIIDOC_INLINE);
+				attributes = trimmed[1..trimmed.len() - 1]
+					.split(',')
+					.map(|a| a.trim().to_owned())
+					.collect();
+				continue;
+			}
+			if let Some(block_title) = trimmed.strip_prefix('.') {
+				if block_title.starts_with(|c: char| !c.is_whitespace() && c != '.') {
+					writer.finish_blocks(&ASCIIDOC_INLINE);
+					title = Some(block_title.to_owned());
+					continue;
+				}
+			}
+
+			// Section titles, whose level is their number of equals signs
+			let level = line.len() - line.trim_start_matches('=').len();
+			if (1..=6).contains(&level) && line[level..].starts_with(' ') {
+				let heading = line[level..].trim();
+				writer.push_html(
+					&heading_collector.render_heading(
+						level as u8,
+						&plain_text(heading, &ASCIIDOC_INLINE),
+						&format_inline(heading, &ASCIIDOC_INLINE),
+					),
+					&ASCIIDOC_INLINE,
+				);
+				continue;
+			}
+
+			if trimmed == "'''" {
+				writer.push_html("<hr />\n", &ASCIIDOC_INLINE);
+				continue;
+			}
+
+			// Tables, whose first row is their header when followed by an empty line
+			if trimmed == "|===" {
+				let mut rows: Vec<Vec<String>> = Vec::new();
+				let mut header = false;
+				while i < lines.len() && lines[i].trim() != "|===" {
+					let row = lines[i].trim();
+					match row.is_empty() {
+						true => header |= rows.len() == 1,
+						false => rows.push(split_table_row(row)),
+					}
+					i += 1;
+				}
+				i += 1;
+				writer.push_html(
+					&render_table(&rows, header, &ASCIIDOC_INLINE),
+					&ASCIIDOC_INLINE,
+				);
+				continue;
+			}
+
+			// Admonition paragraphs, such as `NOTE: text`
+			if let Some(kind) = ADMONITION_KINDS
+				.iter()
+				.find(|kind| trimmed.starts_with(&format!("{kind}: ")))
+			{
+				let mut paragraph = trimmed[kind.len() + 1..].trim().to_owned();
+				while i < lines.len() && !lines[i].trim().is_empty() {
+					paragraph.push('\n');
+					paragraph.push_str(lines[i].trim());
+					i += 1;
+				}
+				writer.push_html(
+					&format!(
+						"{}<p>{}</p>\n{}",
+						self.admonitions.opening_html(kind, title.take().as_deref()),
+						format_inline(&paragraph, &ASCIIDOC_INLINE),
+						self.admonitions.closing_html()
+					),
+					&ASCIIDOC_INLINE,
+				);
+				continue;
+			}
+
+			if let Some((tag, item)) = list_item(trimmed) {
+				writer.list_item(tag, item, &ASCIIDOC_INLINE);
+				continue;
+			}
+			if line.starts_with(char::is_whitespace) && writer.continue_list_item(trimmed) {
+				continue;
+			}
+			writer.paragraph_line(trimmed, &ASCIIDOC_INLINE);
+		}
+		writer.finish(&ASCIIDOC_INLINE)
+	}
+}
@@ -0,0 +1,453 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+check.rs - Checking the links of a built Mokk
+
+The `href` & `src` attributes of every HTML file in a Mokk's output are checked, making sure that internal targets & `#fragment` anchors exist.
+Links beginning with the Mokk's `base_url` are internal, and are checked against the output with the `base_url` removed.
+External links can be checked against an allowlist of URL prefixes. Failures are traced back to the Mokk file, & line, they came from.
+*/
+
+use crate::files::SiteSource;
+use crate::links::split_url_suffix;
+use ahash::{AHashMap, AHashSet};
+use glob::glob;
+use miette::{miette, IntoDiagnostic, WrapErr};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The HTML elements whose contents are not markup
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+/// The attributes linking to other resources
+const LINK_ATTRIBUTES: [&str; 2] = ["href", "src"];
+/// The URL schemes that are neither checked nor treated as paths
+const IGNORED_SCHEMES: [&str; 5] = ["mailto:", "tel:", "data:", "javascript:", "ftp:"];
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+/// A link in a Mokk's output whose target could not be found
+pub struct BrokenLink {
+	/// Path to the HTML file containing the link, relative to the output directory
+	pub output: String,
+	/// Path to the Mokk file the HTML file was rendered from, if known
+	pub source: Option<String>,
+	/// The line of the Mokk file containing the link, if it could be found
+	pub line: Option<usize>,
+	/// The URL of the link
+	pub url: String,
+	/// Why the link is broken
+	pub reason: String,
+}
+
+/// Handle conversion of a BrokenLink object into a string of characters
+impl fmt::Display for BrokenLink {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match (&self.source, self.line) {
+			(Some(source), Some(line)) => write!(f, "{source}:{line}: ")?,
+			(Some(source), None) => write!(f, "{source}: ")?,
+			(None, _) => write!(f, "{}: ", self.output)?,
+		}
+		write!(f, "'{}' {}", self.url, self.reason)
+	}
+}
+
+/// The links & anchors of an HTML file
+#[derive(Eq, PartialEq, Clone, Default, Debug)]
+pub struct HtmlLinks {
+	/// The values of the `href` & `src` attributes, in order
+	pub links: Vec<String>,
+	/// The values of the `id` attributes, and of `name` attributes on anchors
+	pub anchors: AHashSet<String>,
+}
+
+/// Returns the links & anchors of an HTML file
+///
+/// # Arguments
+///
+/// * `html` - The contents of the HTML file
+pub fn parse_html_links(html: &str) -> HtmlLinks {
+	let mut parsed = HtmlLinks::default();
+	let mut i = 0;
+	while let Some(offset) = html[i..].find('<') {
+		let start = i + offset;
+		if html[start..].starts_with("<!--") {
+			i = html[start..]
+				.find("-->")
+				.map_or(html.len(), |end| start + end + "-->".len());
+			continue;
+		}
+		let name: String = html[start + 1..]
+			.chars()
+			.take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+			.collect::<String>()
+			.to_ascii_lowercase();
+		if name.is_empty() {
+			i = start + 1;
+			continue;
+		}
+		let (attributes, tag_end) = parse_attributes(html, start + 1 + name.len());
+		for (attribute, value) in attributes {
+			match attribute.as_str() {
+				"id" => {
+					parsed.anchors.insert(value);
+				}
+				"name" if name == "a" => {
+					parsed.anchors.insert(value);
+				}
+				_ if LINK_ATTRIBUTES.contains(&attribute.as_str()) => parsed.links.push(value),
+				_ => {}
+			}
+		}
+		i = tag_end;
+		if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+			let closing_tag = format!("</{name}");
+			i = html[i..]
+				.to_ascii_lowercase()
+				.find(&closing_tag)
+				.map_or(html.len(), |end| i + end);
+		}
+	}
+	parsed
+}
+
+/// Returns the attributes of an HTML tag, along with the position following the tag
+///
+/// # Arguments
+///
+/// * `html` - The HTML containing the tag
+///
+/// * `start` - The position following the tag's name
+fn parse_attributes(html: &str, start: usize) -> (Vec<(String, String)>, usize) {
+	let bytes = html.as_bytes();
+	let mut attributes = Vec::new();
+	let mut i = start;
+	loop {
+		while i < bytes.len() && (bytes[i].is_ascii_whitespace() || bytes[i] == b'/') {
+			i += 1;
+		}
+		if i >= bytes.len() {
+			return (attributes, bytes.len());
+		}
+		if bytes[i] == b'>' {
+			return (attributes, i + 1);
+		}
+		let name_start = i;
+		while i < bytes.len()
+			&& !matches!(bytes[i], b'=' | b'>' | b'/')
+			&& !bytes[i].is_ascii_whitespace()
+		{
+			i += 1;
+		}
+		let name = html[name_start..i].to_ascii_lowercase();
+		while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+			i += 1;
+		}
+		if bytes.get(i) != Some(&b'=') {
+			attributes.push((name, String::new()));
+			continue;
+		}
+		i += 1;
+		while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+			i += 1;
+		}
+		let value = match bytes.get(i) {
+			Some(quote @ (b'"' | b'\'')) => {
+				let value_start = i + 1;
+				let value_end = html[value_start..]
+					.find(*quote as char)
+					.map_or(html.len(), |end| value_start + end);
+				i = (value_end + 1).min(html.len());
+				&html[value_start..value_end]
+			}
+			_ => {
+				let value_start = i;
+				while i < bytes.len() && bytes[i] != b'>' && !bytes[i].is_ascii_whitespace() {
+					i += 1;
+				}
+				&html[value_start..i]
+			}
+		};
+		attributes.push((name, decode_entities(value)));
+	}
+}
+
+/// Returns text with the character references most common in URLs decoded
+///
+/// # Arguments
+///
+/// * `text` - The text of an HTML attribute
+fn decode_entities(text: &str) -> String {
+	text.replace("&quot;", "\"")
+		.replace("&#39;", "'")
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&amp;", "&")
+}
+
+/// Returns text with its percent-encoded bytes decoded
+///
+/// # Arguments
+///
+/// * `text` - A percent-encoded part of a URL
+pub fn percent_decode(text: &str) -> String {
+	let bytes = text.as_bytes();
+	let mut decoded = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		match (bytes[i], text.get(i + 1..i + 3)) {
+			(b'%', Some(hex)) if u8::from_str_radix(hex, 16).is_ok() => {
+				decoded.push(u8::from_str_radix(hex, 16).unwrap());
+				i += 3;
+			}
+			(byte, _) => {
+				decoded.push(byte);
+				i += 1;
+			}
+		}
+	}
+	String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Reads an allowlist of external URLs; one URL prefix per line, ignoring blank lines & lines beginning with `#`
+///
+/// # Arguments
+///
+/// * `source` - Where the Mokk's files are read from
+///
+/// * `path` - Path to the allowlist file, relative to the root of the Mokk
+pub fn read_allowlist(source: &dyn SiteSource, path: &Path) -> Vec<String> {
+	source
+		.read_to_string(path)
+		.into_diagnostic()
+		.wrap_err(format!(
+			"Unable to read the allowlist of external links at '{}'.",
+			path.display()
+		))
+		.unwrap()
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(str::to_owned)
+		.collect()
+}
+
+/// Returns the line of a Mokk file most likely to contain a link, counting from one
+///
+/// # Arguments
+///
+/// * `source` - Where the Mokk's files are read from
+///
+/// * `path` - Path to the Mokk file
+///
+/// * `url` - The URL of the link, as it appears in the output
+fn find_source_line(source: &dyn SiteSource, path: &str, url: &str) -> Option<usize> {
+	let text = source.read_to_string(Path::new(path)).ok()?;
+	let (path, suffix) = split_url_suffix(url);
+	let file_name = path.rsplit('/').next().unwrap_or_default();
+	// Links to Mokk files & equations are rewritten, so their fragments & file names are searched for too
+	let fragment = suffix.split_once('#').map(|(_, fragment)| fragment);
+	[Some(url), Some(suffix), fragment, Some(file_name)]
+		.into_iter()
+		.flatten()
+		.filter(|candidate| !candidate.is_empty() && *candidate != "#")
+		.find_map(|candidate| text.lines().position(|line| line.contains(candidate)))
+		.map(|line| line + 1)
+}
+
+/// Checks the links of every HTML file in a Mokk's output
+///
+/// # Arguments
+///
+/// * `site_source` - Where the Mokk's files are read from
+///
+/// * `output_directory` - Path to the Mokk's output
+///
+/// * `sources` - The paths to the Mokk files rendered into each output file, keyed by the output file's path relative to `output_directory`
+///
+/// * `allowlist` - The URL prefixes that external links must begin with; if `None`, external links are not checked
+///
+/// * `base_url` - The URL the output is served from, such as `/docs` or `https://example.com/docs`; empty if served from the root of its host
+pub fn check_output(
+	site_source: &dyn SiteSource,
+	output_directory: &Path,
+	sources: &AHashMap<String, String>,
+	allowlist: Option<&[String]>,
+	base_url: &str,
+) -> Vec<BrokenLink> {
+	let mut files: AHashMap<PathBuf, HtmlLinks> = AHashMap::new();
+	for entry in glob(&format!("{}/**/*.html", output_directory.display())).unwrap() {
+		let file = entry.unwrap();
+		let html = fs::read_to_string(&file)
+			.into_diagnostic()
+			.wrap_err(format!("Unable to read the file at '{}'.", file.display()))
+			.unwrap();
+		files.insert(
+			file.strip_prefix(output_directory).unwrap().to_path_buf(),
+			parse_html_links(&html),
+		);
+	}
+
+	let mut broken_links = Vec::new();
+	let mut file_paths: Vec<&PathBuf> = files.keys().collect();
+	file_paths.sort();
+	for file in file_paths {
+		let output = file.to_string_lossy().to_string();
+		let source = sources.get(&output).cloned();
+		for url in &files[file].links {
+			let reason = match check_link(output_directory, file, url, &files, allowlist, base_url)
+			{
+				Ok(()) => continue,
+				Err(reason) => reason,
+			};
+			broken_links.push(BrokenLink {
+				output: output.clone(),
+				line: source
+					.as_deref()
+					.and_then(|source| find_source_line(site_source, source, url)),
+				source: source.clone(),
+				url: url.to_owned(),
+				reason,
+			});
+		}
+	}
+	broken_links
+}
+
+/// Checks that the target of a link exists, returning why it is broken if it does not
+///
+/// # Arguments
+///
+/// * `output_directory` - Path to the Mokk's output
+///
+/// * `file` - Path to the HTML file containing the link, relative to `output_directory`
+///
+/// * `url` - The URL of the link
+///
+/// * `files` - The links & anchors of every HTML file in the output, keyed by their paths relative to `output_directory`
+///
+/// * `allowlist` - The URL prefixes that external links must begin with; if `None`, external links are not checked
+///
+/// * `base_url` - The URL the output is served from; empty if served from the root of its host
+fn check_link(
+	output_directory: &Path,
+	file: &Path,
+	url: &str,
+	files: &AHashMap<PathBuf, HtmlLinks>,
+	allowlist: Option<&[String]>,
+	base_url: &str,
+) -> Result<(), String> {
+	// Links within the `base_url` are resolved from the root of the output
+	let url = &strip_base_url(url, base_url).unwrap_or(url.to_owned());
+	let lowercase_url = url.to_ascii_lowercase();
+	if lowercase_url.starts_with("http://")
+		|| lowercase_url.starts_with("https://")
+		|| lowercase_url.starts_with("//")
+	{
+		return match allowlist {
+			Some(allowlist) if !allowlist.iter().any(|prefix| url.starts_with(prefix)) => {
+				Err("is an external link not in the allowlist".to_owned())
+			}
+			_ => Ok(()),
+		};
+	}
+	if IGNORED_SCHEMES
+		.iter()
+		.any(|scheme| lowercase_url.starts_with(scheme))
+	{
+		return Ok(());
+	}
+
+	let (path, suffix) = split_url_suffix(url);
+	let fragment = suffix
+		.split_once('#')
+		.map(|(_, fragment)| percent_decode(fragment));
+	let path = percent_decode(path);
+	let target = match (path.as_str(), path.strip_prefix('/')) {
+		("", _) => file.to_path_buf(),
+		(_, Some(absolute)) => path_clean::clean(absolute),
+		(relative, None) => {
+			path_clean::clean(file.parent().unwrap_or(Path::new("")).join(relative))
+		}
+	};
+	if target.starts_with("..") {
+		return Err("points outside of the output".to_owned());
+	}
+	// Directories are served by their `index.html`
+	let target = match output_directory.join(&target).is_dir() {
+		true => target.join("index.html"),
+		false => target,
+	};
+	if !output_directory.join(&target).exists() {
+		return Err(format!(
+			"points to '{}', which does not exist",
+			target.display()
+		));
+	}
+
+	match (fragment, files.get(&target)) {
+		(Some(fragment), Some(target_links))
+			if !fragment.is_empty() && !target_links.anchors.contains(&fragment) =>
+		{
+			Err(format!(
+				"points to the anchor '#{fragment}', which does not exist in '{}'",
+				target.display()
+			))
+		}
+		_ => Ok(()),
+	}
+}
+
+/// Returns a URL beginning with a `base_url` as a path from the root of the output, or `None` if it does not begin with the `base_url`
+///
+/// # Arguments
+///
+/// * `url` - The URL of a link
+///
+/// * `base_url` - The URL the output is served from; empty if served from the root of its host
+fn strip_base_url(url: &str, base_url: &str) -> Option<String> {
+	let base_url = base_url.trim_end_matches('/');
+	if base_url.is_empty() {
+		return None;
+	}
+	// Schemes & hosts are case-insensitive
+	let rest = match url.get(..base_url.len()) {
+		Some(prefix) if prefix.eq_ignore_ascii_case(base_url) => &url[base_url.len()..],
+		_ => return None,
+	};
+	match rest.chars().next() {
+		None => Some("/".to_owned()),
+		Some('/') => Some(rest.to_owned()),
+		Some('#' | '?') => Some(format!("/{rest}")),
+		// Links such as `/docsearch` share the `base_url`'s characters, but not its path
+		Some(_) => None,
+	}
+}
+
+/// Returns an error if any links are broken, after listing them
+///
+/// # Arguments
+///
+/// * `broken_links` - The broken links found while checking a Mokk's output
+pub fn report_broken_links(broken_links: &[BrokenLink]) -> miette::Result<()> {
+	for broken_link in broken_links {
+		eprintln!("{broken_link}");
+	}
+	match broken_links.len() {
+		0 => Ok(()),
+		count => Err(miette!("Found {count} broken link(s).")),
+	}
+}
@@ -0,0 +1,361 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+commands.rs - Rendering with external commands
+
+Local tools, such as Graphviz or PlantUML, can render parts of pages: text is piped through a command's standard input, and replaced by its standard output.
+Commands are defined under the `commands` key of the global file:
+```yaml
+commands:
+  graphviz: { command: dot, arguments: ["-Tsvg"], language: dot }
+  rst: { command: pandoc, arguments: ["--from", "rst", "--to", "html"], timeout: 60 }
+```
+Commands with a `language` render the fenced code blocks of that language in Markdown, which are replaced by the command's output.
+Other commands render the whole contents of a page, and only run for pages listing them under the `commands` key of their frontmatter (such as `commands: [rst]`).
+Commands run during rendering, before the stage named by their `before` value (by default, `content`, once Liquid is rendered), & are stopped if they take longer than their `timeout` in seconds.
+Outputs are cached by a hash of the command & its input, in memory & in the Mokk's `.dokkoo/cache/commands` folder, so commands are only run again when their input changes.
+*/
+
+use crate::formats;
+use crate::pipeline::{RenderContext, RenderStage, CONTENT_STAGE};
+use crate::scan::replace_code_blocks;
+use crate::{Build, Page};
+use ahash::AHashMap;
+use miette::{miette, IntoDiagnostic, WrapErr};
+use serde::{Deserialize, Serialize};
+use siphasher::sip128::{Hasher128, SipHasher13};
+use std::fs;
+use std::hash::Hash;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The name of the stages running commands, followed by the name of the stage they run before
+pub const COMMANDS_STAGE: &str = "commands";
+/// The folder within a Mokk where the outputs of commands are cached
+pub const CACHE_FOLDER: &str = ".dokkoo/cache/commands";
+/// The class of the element containing the output of a command rendering a code block
+pub const COMMAND_OUTPUT_CLASS: &str = "command-output";
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+/// Configuration of a command, from the `commands` key of the global file
+pub struct CommandOptions {
+	/// The program to run
+	pub command: String,
+	/// The arguments given to the program
+	pub arguments: Vec<String>,
+	/// The language of the fenced code blocks rendered by the command; without one, the command renders the contents of the pages enabling it
+	pub language: Option<String>,
+	/// The name of the stage of rendering the command runs before
+	pub before: String,
+	/// The longest the command may run for, in seconds
+	pub timeout: u64,
+	/// Whether the command's outputs are cached
+	pub cache: bool,
+}
+
+/// The initial state of a `CommandOptions` object
+impl Default for CommandOptions {
+	fn default() -> Self {
+		Self {
+			command: String::new(),
+			arguments: Vec::new(),
+			language: None,
+			before: CONTENT_STAGE.to_owned(),
+			timeout: 30,
+			cache: true,
+		}
+	}
+}
+
+/// Returns the commands defined in the global file, in the order of their names
+///
+/// # Arguments
+///
+/// * `global` - The global context
+pub fn get_commands(global: &AHashMap<String, serde_yaml::Value>) -> Vec<(String, CommandOptions)> {
+	let mut commands: Vec<(String, CommandOptions)> = match global.get("commands") {
+		Some(c) => serde_yaml::from_value::<AHashMap<String, CommandOptions>>(c.clone())
+			.into_diagnostic()
+			.wrap_err(format!(
+				"Unable to read `commands` value ({:?}) from global file.",
+				c
+			))
+			.unwrap()
+			.into_iter()
+			.collect(),
+		None => Vec::new(),
+	};
+	commands.sort_by(|(a, _), (b, _)| a.cmp(b));
+	commands
+}
+
+/// Returns the stages running the commands defined in the global file, along with the names of the stages they run before
+///
+/// # Arguments
+///
+/// * `global` - The global context
+///
+/// * `root` - Path to the Mokk, containing the cache of outputs
+pub fn command_stages(
+	global: &AHashMap<String, serde_yaml::Value>,
+	root: &str,
+) -> Vec<(String, CommandStage)> {
+	let mut stages: Vec<(String, CommandStage)> = Vec::new();
+	for (name, command) in get_commands(global) {
+		match stages
+			.iter_mut()
+			.find(|(before, _)| *before == command.before)
+		{
+			Some((_, stage)) => stage.commands.push((name, command)),
+			None => {
+				let stage = CommandStage::new(
+					&command.before,
+					Vec::new(),
+					Path::new(root).join(CACHE_FOLDER),
+				);
+				stages.push((command.before.clone(), stage));
+				stages.last_mut().unwrap().1.commands.push((name, command));
+			}
+		}
+	}
+	stages
+}
+
+/// Returns the output of a command given its input, stopping it if it takes longer than its timeout
+///
+/// # Arguments
+///
+/// * `options` - The configuration of the command
+///
+/// * `input` - The text piped to the command's standard input
+pub fn run_command(options: &CommandOptions, input: &str) -> miette::Result<String> {
+	let mut child = Command::new(&options.command)
+		.args(&options.arguments)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.into_diagnostic()
+		.wrap_err(format!("Unable to start '{}'.", options.command))?;
+
+	// Input is written & output is read alongside each other, so that neither pipe fills while the command waits on the other
+	let mut stdin = child.stdin.take().unwrap();
+	let input = input.to_owned();
+	let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+	let mut stdout = child.stdout.take().unwrap();
+	let reader = thread::spawn(move || {
+		let mut output = Vec::new();
+		stdout.read_to_end(&mut output).map(|_| output)
+	});
+	let mut stderr = child.stderr.take().unwrap();
+	let error_reader = thread::spawn(move || {
+		let mut errors = String::new();
+		stderr.read_to_string(&mut errors).map(|_| errors)
+	});
+
+	let deadline = Instant::now() + Duration::from_secs(options.timeout);
+	let status = loop {
+		if let Some(status) = child.try_wait().into_diagnostic()? {
+			break status;
+		}
+		if Instant::now() >= deadline {
+			let _ = child.kill();
+			let _ = child.wait();
+			return Err(miette!(
+				"'{}' did not finish within {} seconds.",
+				options.command,
+				options.timeout
+			));
+		}
+		thread::sleep(Duration::from_millis(10));
+	};
+
+	// Commands may exit without reading all of their input
+	let _ = writer.join();
+	let output = reader.join().unwrap().into_diagnostic()?;
+	let errors = error_reader.join().unwrap().unwrap_or_default();
+	if !status.success() {
+		return Err(miette!(
+			"'{}' exited with {}: {}",
+			options.command,
+			status,
+			errors.trim()
+		));
+	}
+	String::from_utf8(output)
+		.into_diagnostic()
+		.wrap_err(format!("'{}' did not output UTF-8 text.", options.command))
+}
+
+/// Returns the key an output is cached under, a hash of the command & its input
+///
+/// # Arguments
+///
+/// * `options` - The configuration of the command
+///
+/// * `input` - The text piped to the command
+fn cache_key(options: &CommandOptions, input: &str) -> String {
+	let mut hasher = SipHasher13::new();
+	(&options.command, &options.arguments, input).hash(&mut hasher);
+	format!("{:032x}", hasher.finish128().as_u128())
+}
+
+/// Returns text with its blank lines removed, so that Markdown treats it as a single HTML block
+///
+/// # Arguments
+///
+/// * `text` - The text
+fn remove_blank_lines(text: &str) -> String {
+	text.lines()
+		.filter(|line| !line.trim().is_empty())
+		.collect::<Vec<&str>>()
+		.join("\n")
+}
+
+/// A stage of rendering, running commands before another stage
+pub struct CommandStage {
+	/// The name of the stage
+	name: String,
+	/// The commands run, keyed by their names
+	pub commands: Vec<(String, CommandOptions)>,
+	/// The folder the outputs of commands are cached in
+	pub cache_folder: PathBuf,
+	/// The outputs of commands, keyed by the hash of the command & its input
+	cache: Mutex<AHashMap<String, String>>,
+}
+
+impl CommandStage {
+	/// Creates a stage running commands
+	///
+	/// # Arguments
+	///
+	/// * `before` - The name of the stage the commands run before
+	///
+	/// * `commands` - The commands run, keyed by their names
+	///
+	/// * `cache_folder` - The folder the outputs of commands are cached in
+	pub fn new(
+		before: &str,
+		commands: Vec<(String, CommandOptions)>,
+		cache_folder: PathBuf,
+	) -> Self {
+		Self {
+			name: format!("{COMMANDS_STAGE}:{before}"),
+			commands,
+			cache_folder,
+			cache: Mutex::new(AHashMap::new()),
+		}
+	}
+
+	/// Returns the output of a command for a page, from the cache if the command has been run with the same input
+	///
+	/// # Arguments
+	///
+	/// * `name` - The name of the command
+	///
+	/// * `options` - The configuration of the command
+	///
+	/// * `input` - The text piped to the command
+	///
+	/// * `page` - The `.mokkf` file's context as a `Page`
+	fn run(&self, name: &str, options: &CommandOptions, input: &str, page: &Page) -> String {
+		if !options.cache {
+			return run_command(options, input)
+				.wrap_err(format!(
+					"Unable to run the command '{name}' for '{}'.",
+					page.path()
+				))
+				.unwrap();
+		}
+
+		let key = cache_key(options, input);
+		if let Some(output) = self.cache.lock().unwrap().get(&key) {
+			return output.clone();
+		}
+		let cache_path = self.cache_folder.join(&key);
+		let output = match fs::read_to_string(&cache_path) {
+			Ok(output) => output,
+			Err(_) => {
+				let output = run_command(options, input)
+					.wrap_err(format!(
+						"Unable to run the command '{name}' for '{}'.",
+						page.path()
+					))
+					.unwrap();
+				// Outputs are still cached in memory if they cannot be cached on disk
+				let _ = fs::create_dir_all(&self.cache_folder)
+					.and_then(|_| fs::write(&cache_path, &output));
+				output
+			}
+		};
+		self.cache.lock().unwrap().insert(key, output.clone());
+		output
+	}
+}
+
+impl RenderStage for CommandStage {
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn process(&self, _build: &Build, context: &RenderContext, text: String) -> String {
+		// Commands are not run on the contents of sanitised pages, which are untrusted
+		if !context.is_trusted() {
+			return text;
+		}
+		let page = context.page;
+		let enabled: Vec<String> = match page.data.get("commands") {
+			Some(c) => serde_yaml::from_value(c.clone())
+				.into_diagnostic()
+				.wrap_err(format!(
+					"Unable to read `commands` value ({:?}) as a list of names in frontmatter of file '{}'.",
+					c,
+					page.path()
+				))
+				.unwrap(),
+			None => Vec::new(),
+		};
+
+		let mut text = text;
+		for (name, options) in &self.commands {
+			match &options.language {
+				Some(language) if context.format == formats::MARKDOWN => {
+					text = replace_code_blocks(&text, |info, code| {
+						match info.split_whitespace().next() == Some(language.as_str()) {
+							true => Some(format!(
+								"<div class=\"{COMMAND_OUTPUT_CLASS} language-{language}\">\n{}\n</div>\n",
+								remove_blank_lines(&self.run(name, options, code, page))
+							)),
+							false => None,
+						}
+					});
+				}
+				Some(_) => {}
+				None if context.content && enabled.contains(name) => {
+					text = self.run(name, options, &text, page)
+				}
+				None => {}
+			}
+		}
+		text
+	}
+}
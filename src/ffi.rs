@@ -0,0 +1,243 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+ffi.rs - The C API of Dokkoo
+
+Dokkoo's shared & static libraries (`libdokkoo.so` & `libdokkoo.a`) expose these functions to C, with their declarations in `include/dokkoo.h`.
+Builds are opaque handles, created & freed by Dokkoo. Strings returned by Dokkoo are owned by the caller, and freed with `dokkoo_string_free`.
+Functions that fail return `NULL` or a negative number; the reason is then given by `dokkoo_last_error`.
+*/
+
+use crate::files::{DiskSource, SiteSource};
+use crate::options::BuildOptions;
+use crate::{formats, Build, Page};
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+
+thread_local! {
+	/// The message of the last error to occur on this thread
+	static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Records the message of an error, for retrieval with `dokkoo_last_error`
+///
+/// # Arguments
+///
+/// * `message` - The message of the error
+fn set_last_error(message: String) {
+	let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+	LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// Runs a function, returning `None` & recording the error if it panics
+///
+/// # Arguments
+///
+/// * `function` - The function to run
+fn catch_error<T>(function: impl FnOnce() -> T) -> Option<T> {
+	crate::catch_error(function).map_err(set_last_error).ok()
+}
+
+/// Returns a string given by C, recording an error if it is `NULL` or not UTF-8
+///
+/// # Arguments
+///
+/// * `string` - A nul-terminated string
+///
+/// * `name` - The name of the argument, used in the error message
+///
+/// # Safety
+///
+/// `string` must be `NULL` or point to a valid nul-terminated string
+unsafe fn read_string(string: *const c_char, name: &str) -> Option<String> {
+	if string.is_null() {
+		set_last_error(format!("`{name}` must not be NULL."));
+		return None;
+	}
+	match CStr::from_ptr(string).to_str() {
+		Ok(string) => Some(string.to_owned()),
+		Err(e) => {
+			set_last_error(format!("`{name}` is not valid UTF-8: {e}"));
+			None
+		}
+	}
+}
+
+/// Creates a build of the Mokk at the given path. Returns `NULL` on failure.
+///
+/// # Arguments
+///
+/// * `path` - Path to a Mokk, or `NULL` to use the current directory
+///
+/// # Safety
+///
+/// `path` must be `NULL` or point to a valid nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn dokkoo_build_new(path: *const c_char) -> *mut Build {
+	let path = match path.is_null() {
+		true => ".".to_owned(),
+		false => match read_string(path, "path") {
+			Some(path) => path,
+			None => return ptr::null_mut(),
+		},
+	};
+	let source = Arc::new(DiskSource::new(&path));
+	if !source.is_dir(Path::new(".")) {
+		set_last_error(format!("Could not read a Mokk at {path}."));
+		return ptr::null_mut();
+	}
+	catch_error(|| Box::into_raw(Box::new(BuildOptions::new(path).source(source).build())))
+		.unwrap_or(ptr::null_mut())
+}
+
+/// Frees a build
+///
+/// # Arguments
+///
+/// * `build` - A build created by `dokkoo_build_new`, or `NULL`
+///
+/// # Safety
+///
+/// `build` must be `NULL` or a build created by `dokkoo_build_new` that has not already been freed
+#[no_mangle]
+pub unsafe extern "C" fn dokkoo_build_free(build: *mut Build) {
+	if !build.is_null() {
+		drop(Box::from_raw(build));
+	}
+}
+
+/// Renders text as the contents of a page, returning the HTML, or `NULL` on failure. The HTML is freed with `dokkoo_string_free`.
+///
+/// # Arguments
+///
+/// * `build` - A build created by `dokkoo_build_new`
+///
+/// * `text` - The text to render, which may use Liquid
+///
+/// * `format` - The markup format of the text, such as `markdown`, `html` or `org`; `NULL` for Markdown
+///
+/// # Safety
+///
+/// `build` must be a build created by `dokkoo_build_new`, and `text` & `format` must be `NULL` or point to valid nul-terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn dokkoo_render(
+	build: *mut Build,
+	text: *const c_char,
+	format: *const c_char,
+) -> *mut c_char {
+	if build.is_null() {
+		set_last_error("`build` must not be NULL.".to_owned());
+		return ptr::null_mut();
+	}
+	let build = &mut *build;
+	let Some(text) = read_string(text, "text") else {
+		return ptr::null_mut();
+	};
+	let format = match format.is_null() {
+		true => formats::MARKDOWN.to_owned(),
+		false => match read_string(format, "format") {
+			Some(format) => format.to_lowercase(),
+			None => return ptr::null_mut(),
+		},
+	};
+	catch_error(|| {
+		let page = Page {
+			markdown: format == formats::MARKDOWN,
+			format,
+			math: true,
+			minify: build.global_context.1.minify,
+			..Default::default()
+		};
+		build.heading_collector.clear();
+		build.render_content(&page, &text)
+	})
+	.and_then(|rendered| match CString::new(rendered) {
+		Ok(rendered) => Some(rendered.into_raw()),
+		Err(e) => {
+			set_last_error(format!("Rendered text contains a nul character: {e}"));
+			None
+		}
+	})
+	.unwrap_or(ptr::null_mut())
+}
+
+/// Builds the Mokk at the given path into its output folder. Returns `0` on success, or `-1` on failure, including when any page could not be compiled.
+///
+/// # Arguments
+///
+/// * `path` - Path to a Mokk
+///
+/// # Safety
+///
+/// `path` must point to a valid nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn dokkoo_build_directory(path: *const c_char) -> c_int {
+	let Some(path) = read_string(path, "path") else {
+		return -1;
+	};
+	let path = match DiskSource::new(&path).canonicalize(Path::new(".")) {
+		Some(path) => path.to_string_lossy().to_string(),
+		None => {
+			set_last_error(format!("Could not read a Mokk at {path}."));
+			return -1;
+		}
+	};
+	let source = Arc::new(DiskSource::new(&path));
+	catch_error(|| {
+		let mut build = BuildOptions::new(path).source(source).build();
+		let pages = build.load_pages();
+		build.build_pages(pages)
+	})
+	.map_or(-1, |report| match report.errors.first() {
+		// The other pages are still built, but the first error is given
+		Some(error) => {
+			set_last_error(format!("Error in '{}': {}", error.path, error.message));
+			-1
+		}
+		None => 0,
+	})
+}
+
+/// Returns the message of the last error to occur on this thread, or `NULL` if none has. The message belongs to Dokkoo, and is valid until the next error on this thread.
+#[no_mangle]
+pub extern "C" fn dokkoo_last_error() -> *const c_char {
+	LAST_ERROR.with(|last_error| {
+		last_error
+			.borrow()
+			.as_ref()
+			.map_or(ptr::null(), |message| message.as_ptr())
+	})
+}
+
+/// Frees a string returned by Dokkoo
+///
+/// # Arguments
+///
+/// * `string` - A string returned by Dokkoo, or `NULL`
+///
+/// # Safety
+///
+/// `string` must be `NULL` or a string returned by Dokkoo that has not already been freed
+#[no_mangle]
+pub unsafe extern "C" fn dokkoo_string_free(string: *mut c_char) {
+	if !string.is_null() {
+		drop(CString::from_raw(string));
+	}
+}
@@ -0,0 +1,361 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+files.rs - Access to the files of a Mokk
+
+The files of a Mokk (its pages, layouts, snippets & global file) are read from a site source given to a build, with paths relative to the root of the Mokk.
+Mokks may be read from a folder on disk (with the `fs` feature, enabled by default), from memory, or from a tar archive.
+Without the `fs` feature, such as when compiled to WebAssembly, builds read from an empty source by default; pages are rendered without layouts, snippets or a global file.
+*/
+
+use ahash::AHashMap;
+use glob::{MatchOptions, Pattern};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// Where the files of a Mokk are read from
+pub trait SiteSource: Send + Sync {
+	/// Returns the contents of a file
+	///
+	/// # Arguments
+	///
+	/// * `path` - The path to the file
+	fn read_to_string(&self, path: &Path) -> io::Result<String>;
+
+	/// Returns the contents of a file as bytes, such as those of a WebAssembly module; by default, only text files can be read
+	///
+	/// # Arguments
+	///
+	/// * `path` - The path to the file
+	fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+		self.read_to_string(path).map(String::into_bytes)
+	}
+
+	/// Returns the paths to the files & folders matching a glob pattern, such as `./snippets/**/*`
+	///
+	/// # Arguments
+	///
+	/// * `pattern` - The glob pattern to match
+	fn find(&self, pattern: &str) -> Vec<PathBuf>;
+
+	/// Returns whether a path is a folder
+	///
+	/// # Arguments
+	///
+	/// * `path` - The path to check
+	fn is_dir(&self, path: &Path) -> bool;
+
+	/// Returns the canonical form of a path, if it exists
+	///
+	/// # Arguments
+	///
+	/// * `path` - The path to canonicalise
+	fn canonicalize(&self, path: &Path) -> Option<PathBuf>;
+
+	/// Returns the canonical form of a path, or the path as given if it does not exist
+	///
+	/// # Arguments
+	///
+	/// * `path` - The path to canonicalise
+	fn canonical_path(&self, path: &Path) -> PathBuf {
+		self.canonicalize(path).unwrap_or(path.to_path_buf())
+	}
+}
+
+/// Returns the source builds read from by default: the Mokk's folder on disk, or an empty source without the `fs` feature
+///
+/// # Arguments
+///
+/// * `root` - Path to the Mokk
+pub fn default_source(root: &str) -> Arc<dyn SiteSource> {
+	#[cfg(feature = "fs")]
+	return Arc::new(DiskSource::new(root));
+	#[cfg(not(feature = "fs"))]
+	return {
+		let _ = root;
+		Arc::new(MemorySource::default())
+	};
+}
+
+/// A Mokk in a folder on disk
+#[cfg(feature = "fs")]
+#[derive(Clone, Debug)]
+pub struct DiskSource {
+	/// The folder containing the Mokk
+	pub root: PathBuf,
+}
+
+#[cfg(feature = "fs")]
+impl DiskSource {
+	/// Reads a Mokk from a folder
+	///
+	/// # Arguments
+	///
+	/// * `root` - The folder containing the Mokk
+	pub fn new(root: impl Into<PathBuf>) -> Self {
+		Self { root: root.into() }
+	}
+
+	/// Returns a path relative to the root of the Mokk as a path on disk; absolute paths are left as they are
+	///
+	/// # Arguments
+	///
+	/// * `path` - The path to resolve
+	pub fn resolve(&self, path: &Path) -> PathBuf {
+		self.root.join(path)
+	}
+}
+
+#[cfg(feature = "fs")]
+impl SiteSource for DiskSource {
+	fn read_to_string(&self, path: &Path) -> io::Result<String> {
+		std::fs::read_to_string(self.resolve(path))
+	}
+
+	fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+		std::fs::read(self.resolve(path))
+	}
+
+	/// Paths are found relative to the root of the Mokk, unless the pattern is absolute
+	fn find(&self, pattern: &str) -> Vec<PathBuf> {
+		let pattern = path_clean::clean(pattern);
+		let relative = !pattern.is_absolute();
+		let pattern = match relative {
+			true => format!(
+				"{}/{}",
+				Pattern::escape(&self.root.to_string_lossy()),
+				pattern.display()
+			),
+			false => pattern.display().to_string(),
+		};
+		match glob::glob(&pattern) {
+			Ok(paths) => paths
+				.filter_map(Result::ok)
+				.map(|path| match relative {
+					true => path
+						.strip_prefix(&self.root)
+						.map(Path::to_path_buf)
+						.unwrap_or(path),
+					false => path,
+				})
+				.collect(),
+			Err(_) => Vec::new(),
+		}
+	}
+
+	fn is_dir(&self, path: &Path) -> bool {
+		self.resolve(path).is_dir()
+	}
+
+	fn canonicalize(&self, path: &Path) -> Option<PathBuf> {
+		std::fs::canonicalize(self.resolve(path)).ok()
+	}
+}
+
+/// Returns a path relative to the root of a Mokk in memory, without `.` or `..` components
+///
+/// # Arguments
+///
+/// * `path` - The path to normalise
+fn normalize(path: &Path) -> String {
+	path_clean::clean(path).to_string_lossy().replace('\\', "/")
+}
+
+/// Returns a relative path without `.` or `..` components, or `None` if it is absolute or leads outside of the folder it is relative to
+///
+/// # Arguments
+///
+/// * `path` - The path, such as that of a file given by a script or plugin
+pub fn contained_path(path: &str) -> Option<PathBuf> {
+	Some(path_clean::clean(path)).filter(|p| {
+		p.components()
+			.all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+	})
+}
+
+/// A Mokk held in memory, such as one given by a test, a server or an editor
+#[derive(Clone, Debug, Default)]
+pub struct MemorySource {
+	/// The contents of each file, keyed by its path relative to the root of the Mokk
+	pub files: AHashMap<String, String>,
+}
+
+impl MemorySource {
+	/// Creates a Mokk in memory from its files
+	///
+	/// # Arguments
+	///
+	/// * `files` - The path & contents of each file
+	pub fn new(files: impl IntoIterator<Item = (String, String)>) -> Self {
+		let mut source = Self::default();
+		for (path, text) in files {
+			source.insert(&path, text);
+		}
+		source
+	}
+
+	/// Adds a file, replacing any file already at its path
+	///
+	/// # Arguments
+	///
+	/// * `path` - The path to the file, relative to the root of the Mokk
+	///
+	/// * `text` - The contents of the file
+	pub fn insert(&mut self, path: &str, text: String) {
+		self.files.insert(normalize(Path::new(path)), text);
+	}
+}
+
+impl SiteSource for MemorySource {
+	fn read_to_string(&self, path: &Path) -> io::Result<String> {
+		self.files
+			.get(&normalize(path))
+			.cloned()
+			.ok_or(not_found(path))
+	}
+
+	fn find(&self, pattern: &str) -> Vec<PathBuf> {
+		find_paths(self.files.keys(), pattern)
+	}
+
+	fn is_dir(&self, path: &Path) -> bool {
+		contains_folder(self.files.keys(), path)
+	}
+
+	fn canonicalize(&self, path: &Path) -> Option<PathBuf> {
+		let normalized = normalize(path);
+		match self.files.contains_key(&normalized) || self.is_dir(path) {
+			true => Some(PathBuf::from(normalized)),
+			false => None,
+		}
+	}
+}
+
+/// Returns the error given when a file of a Mokk in memory does not exist
+///
+/// # Arguments
+///
+/// * `path` - The path to the file
+fn not_found(path: &Path) -> io::Error {
+	io::Error::new(
+		io::ErrorKind::NotFound,
+		format!("There is no file at '{}'.", path.display()),
+	)
+}
+
+/// Returns the paths of the files of a Mokk in memory matching a glob pattern, in order
+///
+/// # Arguments
+///
+/// * `files` - The normalised paths of the files
+///
+/// * `pattern` - The glob pattern to match
+fn find_paths<'a>(files: impl Iterator<Item = &'a String>, pattern: &str) -> Vec<PathBuf> {
+	let Ok(pattern) = Pattern::new(&normalize(Path::new(pattern))) else {
+		return Vec::new();
+	};
+	let options = MatchOptions {
+		require_literal_separator: true,
+		..Default::default()
+	};
+	let mut paths: Vec<PathBuf> = files
+		.filter(|path| pattern.matches_with(path, options))
+		.map(PathBuf::from)
+		.collect();
+	paths.sort();
+	paths
+}
+
+/// Returns whether a path is a folder containing any of the files of a Mokk in memory
+///
+/// # Arguments
+///
+/// * `files` - The normalised paths of the files
+///
+/// * `path` - The path to check
+fn contains_folder<'a>(mut files: impl Iterator<Item = &'a String>, path: &Path) -> bool {
+	let path = normalize(path);
+	let prefix = match path.as_str() {
+		"." => String::new(),
+		path => format!("{path}/"),
+	};
+	files.any(|file| file.starts_with(&prefix))
+}
+
+/// A Mokk in a tar archive, read into memory; its paths are relative to the root of the archive
+#[derive(Clone, Debug, Default)]
+pub struct ArchiveSource {
+	/// The contents of each file of the archive, keyed by its path relative to the root of the archive
+	pub files: AHashMap<String, Vec<u8>>,
+}
+
+impl ArchiveSource {
+	/// Reads a Mokk from a tar archive
+	///
+	/// # Arguments
+	///
+	/// * `archive` - The tar archive, which may be wrapped in a decompressor
+	pub fn new(archive: impl Read) -> io::Result<Self> {
+		let mut files = AHashMap::new();
+		for entry in tar::Archive::new(archive).entries()? {
+			let mut entry = entry?;
+			if !entry.header().entry_type().is_file() {
+				continue;
+			}
+			let path = normalize(&entry.path()?);
+			let mut contents = Vec::new();
+			entry.read_to_end(&mut contents)?;
+			files.insert(path, contents);
+		}
+		Ok(Self { files })
+	}
+}
+
+impl SiteSource for ArchiveSource {
+	fn read_to_string(&self, path: &Path) -> io::Result<String> {
+		String::from_utf8(self.read(path)?).map_err(|_| {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("The file at '{}' is not UTF-8 text.", path.display()),
+			)
+		})
+	}
+
+	fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+		self.files
+			.get(&normalize(path))
+			.cloned()
+			.ok_or(not_found(path))
+	}
+
+	fn find(&self, pattern: &str) -> Vec<PathBuf> {
+		find_paths(self.files.keys(), pattern)
+	}
+
+	fn is_dir(&self, path: &Path) -> bool {
+		contains_folder(self.files.keys(), path)
+	}
+
+	fn canonicalize(&self, path: &Path) -> Option<PathBuf> {
+		let normalized = normalize(path);
+		match self.files.contains_key(&normalized) || self.is_dir(path) {
+			true => Some(PathBuf::from(normalized)),
+			false => None,
+		}
+	}
+}
@@ -0,0 +1,416 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+formats.rs - Markup formats of Mokk files
+
+A Mokk file's contents are written in Markdown by default. Other formats are chosen with the `format` frontmatter key, or with an extension preceding `.mokkf` (e.g. `notes.org.mokkf`).
+Formats other than Markdown & raw HTML are rendered by the content renderers registered with a build.
+*/
+
+use crate::escape_html;
+use crate::toc::HeadingCollector;
+
+/// The name of the Markdown format
+pub const MARKDOWN: &str = "markdown";
+/// The name of the raw HTML format, whose contents are not rendered
+pub const HTML: &str = "html";
+/// The name of the Org-mode format
+pub const ORG: &str = "org";
+/// The name of the AsciiDoc format
+pub const ASCIIDOC: &str = "asciidoc";
+/// The name of the Jupyter notebook format
+pub const NOTEBOOK: &str = "ipynb";
+
+/// Renders the contents of Mokk files written in a particular format as HTML
+pub trait ContentRenderer: Send + Sync {
+	/// Returns a Mokk file's contents rendered as HTML
+	///
+	/// # Arguments
+	///
+	/// * `text` - The contents of the Mokk file, after Liquid has been rendered
+	///
+	/// * `heading_collector` - Collects the headings of the Mokk file, giving them IDs
+	///
+	/// * `math` - Whether or not to render LaTeX math; the math of templates is found & rendered outside of the renderer
+	fn render(&self, text: &str, heading_collector: &HeadingCollector, math: bool) -> String;
+
+	/// Whether the format's text is a template, in which Liquid is rendered & LaTeX math is found before the renderer is given it; formats of structured data, such as notebooks, are not
+	fn is_template(&self) -> bool {
+		true
+	}
+}
+
+/// Returns the format implied by a file extension, if any
+///
+/// # Arguments
+///
+/// * `extension` - A file extension, without the leading `.`
+pub fn format_from_extension(extension: &str) -> Option<&'static str> {
+	match extension.to_ascii_lowercase().as_str() {
+		"md" | "markdown" => Some(MARKDOWN),
+		"html" | "htm" => Some(HTML),
+		"org" => Some(ORG),
+		"adoc" | "asciidoc" | "asc" => Some(ASCIIDOC),
+		"ipynb" => Some(NOTEBOOK),
+		_ => None,
+	}
+}
+
+/// The inline syntax of a markup format
+pub struct InlineSyntax {
+	/// The characters delimiting formatted text, along with the HTML element each is rendered as & whether the text within is verbatim
+	pub delimiters: &'static [(char, &'static str, bool)],
+	/// Renders a link at the start of some text, returning its HTML along with its length
+	pub link: fn(&str, &InlineSyntax) -> Option<(String, usize)>,
+}
+
+/// Renders text written in a markup format's inline syntax as HTML
+///
+/// # Arguments
+///
+/// * `text` - The text to render
+///
+/// * `syntax` - The inline syntax of the format
+pub fn format_inline(text: &str, syntax: &InlineSyntax) -> String {
+	let mut output = String::with_capacity(text.len());
+	let mut previous: Option<char> = None;
+	let mut i = 0;
+	while i < text.len() {
+		let rest = &text[i..];
+		if let Some((html, length)) = (syntax.link)(rest, syntax) {
+			output.push_str(&html);
+			i += length;
+			previous = text[..i].chars().last();
+			continue;
+		}
+		// HTML, such as that given by Liquid, is left as is
+		if let Some(length) = html_length(rest) {
+			output.push_str(&rest[..length]);
+			i += length;
+			previous = text[..i].chars().last();
+			continue;
+		}
+		let c = rest.chars().next().unwrap();
+		let delimiter = syntax
+			.delimiters
+			.iter()
+			.find(|(delimiter, _, _)| *delimiter == c);
+		// Delimiters only open formatting at the start of a word
+		let opens =
+			previous.is_none_or(|p| p.is_whitespace() || (p.is_ascii_punctuation() && p != c));
+		if let (Some((_, tag, verbatim)), true) = (delimiter, opens) {
+			if let Some(end) = find_closing_delimiter(rest, c) {
+				let inner = &rest[c.len_utf8()..end];
+				let inner_html = match verbatim {
+					true => escape_html(inner),
+					false => format_inline(inner, syntax),
+				};
+				output.push_str(&format!("<{tag}>{inner_html}</{tag}>"));
+				i += end + c.len_utf8();
+				previous = Some(c);
+				continue;
+			}
+		}
+		output.push_str(&escape_html(&c.to_string()));
+		i += c.len_utf8();
+		previous = Some(c);
+	}
+	output
+}
+
+/// The HTML elements whose contents are left as is when rendering inline markup, along with the elements themselves
+const RAW_INLINE_ELEMENTS: [&str; 6] = ["pre", "code", "script", "style", "math", "svg"];
+
+/// Returns the length of the HTML at the start of some text, if it begins with an HTML tag, comment or character reference.
+/// Elements whose contents are not markup, such as `<code>`, are included up to their closing tags.
+///
+/// # Arguments
+///
+/// * `text` - The text, possibly beginning with HTML
+fn html_length(text: &str) -> Option<usize> {
+	if let Some(comment) = text.strip_prefix("<!--") {
+		return comment
+			.find("-->")
+			.map(|end| "<!--".len() + end + "-->".len());
+	}
+	if let Some(reference) = text.strip_prefix('&') {
+		let end = reference.find(';')?;
+		let name = reference[..end]
+			.strip_prefix('#')
+			.unwrap_or(&reference[..end]);
+		return (!name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric()))
+			.then_some(end + 2);
+	}
+	let tag = text.strip_prefix('<')?;
+	let name_start = tag.strip_prefix('/').unwrap_or(tag);
+	if !name_start.starts_with(|c: char| c.is_ascii_alphabetic()) {
+		return None;
+	}
+	let name_length = name_start
+		.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+		.unwrap_or(name_start.len());
+	let name = name_start[..name_length].to_ascii_lowercase();
+	// The name must end the tag or be followed by its attributes
+	if !name_start[name_length..].starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+		return None;
+	}
+	let end = tag.find('>')? + 2;
+	if tag.starts_with('/') || !RAW_INLINE_ELEMENTS.contains(&name.as_str()) {
+		return Some(end);
+	}
+	let closing_tag = format!("</{name}>");
+	Some(
+		text[end..]
+			.to_ascii_lowercase()
+			.find(&closing_tag)
+			.map_or(end, |close| end + close + closing_tag.len()),
+	)
+}
+
+/// Returns the text of inline markup without its formatting, such as for the text of a heading
+///
+/// # Arguments
+///
+/// * `text` - The text to render
+///
+/// * `syntax` - The inline syntax of the format
+pub fn plain_text(text: &str, syntax: &InlineSyntax) -> String {
+	let html = format_inline(text, syntax);
+	let mut plain = String::with_capacity(html.len());
+	let mut in_tag = false;
+	for c in html.chars() {
+		match c {
+			'<' => in_tag = true,
+			'>' => in_tag = false,
+			c if !in_tag => plain.push(c),
+			_ => {}
+		}
+	}
+	plain
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&quot;", "\"")
+		.replace("&#39;", "'")
+		.replace("&amp;", "&")
+}
+
+/// Returns the position of the delimiter closing formatted text, if there is one
+///
+/// # Arguments
+///
+/// * `text` - The text, beginning with the opening delimiter
+///
+/// * `delimiter` - The delimiter
+fn find_closing_delimiter(text: &str, delimiter: char) -> Option<usize> {
+	let start = delimiter.len_utf8();
+	if text[start..].chars().next().is_none_or(char::is_whitespace) {
+		return None;
+	}
+	let mut previous = None;
+	for (offset, c) in text[start..].char_indices() {
+		let position = start + offset;
+		if c == delimiter && previous.is_some_and(|p: char| !p.is_whitespace()) {
+			let next = text[position + c.len_utf8()..].chars().next();
+			if next.is_none_or(|n| n.is_whitespace() || n.is_ascii_punctuation()) {
+				return Some(position);
+			}
+		}
+		previous = Some(c);
+	}
+	None
+}
+
+/// Returns the text of a table row's cells, split by `|`
+///
+/// # Arguments
+///
+/// * `row` - A row of a table, such as `| a | b |`
+pub fn split_table_row(row: &str) -> Vec<String> {
+	let row = row.trim();
+	let row = row.strip_prefix('|').unwrap_or(row);
+	let row = row.strip_suffix('|').unwrap_or(row);
+	row.split('|').map(|cell| cell.trim().to_owned()).collect()
+}
+
+/// Renders a table as HTML
+///
+/// # Arguments
+///
+/// * `rows` - The text of each cell, row by row
+///
+/// * `header` - Whether the first row is the table's header
+///
+/// * `syntax` - The inline syntax of the format
+pub fn render_table(rows: &[Vec<String>], header: bool, syntax: &InlineSyntax) -> String {
+	let mut html = String::from("<table>\n");
+	for (index, row) in rows.iter().enumerate() {
+		let (cell_tag, section) = match (header, index) {
+			(true, 0) => ("th", Some("thead")),
+			(true, 1) | (false, 0) => ("td", Some("tbody")),
+			_ => ("td", None),
+		};
+		if let Some(section) = section {
+			if index > 0 {
+				html.push_str("</thead>\n");
+			}
+			html.push_str(&format!("<{section}>\n"));
+		}
+		html.push_str("<tr>");
+		for cell in row {
+			html.push_str(&format!(
+				"<{cell_tag}>{}</{cell_tag}>",
+				format_inline(cell, syntax)
+			));
+		}
+		html.push_str("</tr>\n");
+	}
+	match (header, rows.len()) {
+		(true, 1) => html.push_str("</thead>\n"),
+		(_, 0) => {}
+		_ => html.push_str("</tbody>\n"),
+	}
+	html.push_str("</table>\n");
+	html
+}
+
+#[derive(Default)]
+/// Writes the blocks of a document as HTML, gathering the lines of paragraphs & the items of lists
+pub struct BlockWriter {
+	/// The HTML written so far
+	html: String,
+	/// The lines of the paragraph being written
+	paragraph: Vec<String>,
+	/// The HTML element of the list being written, along with the text of its items
+	list: Option<(&'static str, Vec<String>)>,
+}
+
+impl BlockWriter {
+	/// Adds a line to the paragraph being written, starting one if needed
+	///
+	/// # Arguments
+	///
+	/// * `line` - A line of a paragraph
+	///
+	/// * `syntax` - The inline syntax of the format
+	pub fn paragraph_line(&mut self, line: &str, syntax: &InlineSyntax) {
+		self.finish_list(syntax);
+		self.paragraph.push(line.trim().to_owned());
+	}
+
+	/// Adds an item to the list being written, starting one if needed
+	///
+	/// # Arguments
+	///
+	/// * `tag` - The HTML element of the list; `ul` or `ol`
+	///
+	/// * `item` - The text of the item
+	///
+	/// * `syntax` - The inline syntax of the format
+	pub fn list_item(&mut self, tag: &'static str, item: &str, syntax: &InlineSyntax) {
+		self.finish_paragraph(syntax);
+		if self
+			.list
+			.as_ref()
+			.is_some_and(|(list_tag, _)| *list_tag != tag)
+		{
+			self.finish_list(syntax);
+		}
+		self.list
+			.get_or_insert((tag, Vec::new()))
+			.1
+			.push(item.trim().to_owned());
+	}
+
+	/// Adds a line to the last item of the list being written, returning whether a list is being written
+	///
+	/// # Arguments
+	///
+	/// * `line` - A line continuing the last item
+	pub fn continue_list_item(&mut self, line: &str) -> bool {
+		match self.list.as_mut().and_then(|(_, items)| items.last_mut()) {
+			Some(item) => {
+				item.push(' ');
+				item.push_str(line.trim());
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Writes a block of HTML, after finishing any paragraph or list being written
+	///
+	/// # Arguments
+	///
+	/// * `html` - The HTML of the block
+	///
+	/// * `syntax` - The inline syntax of the format
+	pub fn push_html(&mut self, html: &str, syntax: &InlineSyntax) {
+		self.finish_blocks(syntax);
+		self.html.push_str(html);
+	}
+
+	/// Finishes any paragraph or list being written
+	///
+	/// # Arguments
+	///
+	/// * `syntax` - The inline syntax of the format
+	pub fn finish_blocks(&mut self, syntax: &InlineSyntax) {
+		self.finish_paragraph(syntax);
+		self.finish_list(syntax);
+	}
+
+	/// Finishes the document, returning its HTML
+	///
+	/// # Arguments
+	///
+	/// * `syntax` - The inline syntax of the format
+	pub fn finish(mut self, syntax: &InlineSyntax) -> String {
+		self.finish_blocks(syntax);
+		self.html
+	}
+
+	/// Finishes the paragraph being written, if any
+	///
+	/// # Arguments
+	///
+	/// * `syntax` - The inline syntax of the format
+	fn finish_paragraph(&mut self, syntax: &InlineSyntax) {
+		if !self.paragraph.is_empty() {
+			self.html.push_str(&format!(
+				"<p>{}</p>\n",
+				format_inline(&self.paragraph.join("\n"), syntax)
+			));
+			self.paragraph.clear();
+		}
+	}
+
+	/// Finishes the list being written, if any
+	///
+	/// # Arguments
+	///
+	/// * `syntax` - The inline syntax of the format
+	fn finish_list(&mut self, syntax: &InlineSyntax) {
+		if let Some((tag, items)) = self.list.take() {
+			self.html.push_str(&format!("<{tag}>\n"));
+			for item in items {
+				self.html
+					.push_str(&format!("<li>{}</li>\n", format_inline(&item, syntax)));
+			}
+			self.html.push_str(&format!("</{tag}>\n"));
+		}
+	}
+}
@@ -0,0 +1,775 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+highlight.rs - Syntax highlighting of code
+
+Code is highlighted using Syntect, either with inline styles or with CSS classes accompanied by a generated stylesheet.
+*/
+
+use crate::escape_html;
+use crate::files::{MemorySource, SiteSource};
+use ahash::AHashMap;
+use comrak::nodes::{AstNode, NodeHtmlBlock, NodeValue};
+use miette::{miette, IntoDiagnostic, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::io::{self, Cursor, Write};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, Theme, ThemeSet};
+use syntect::html::{
+	append_highlighted_html_for_styled_line, css_for_theme_with_class_style,
+	line_tokens_to_classed_spans, ClassStyle, IncludeBackground,
+};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxDefinition, SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+/// The prefix given to every CSS class emitted when highlighting with classes
+pub const CLASS_PREFIX: &str = "syn-";
+
+/// The CSS class given to a code block's title
+pub const TITLE_CLASS: &str = "code-title";
+
+/// The CSS class given to each line of a code block
+pub const LINE_CLASS: &str = "line";
+
+/// The CSS class given to the number of a line in a code block
+pub const LINE_NUMBER_CLASS: &str = "line-number";
+
+/// The CSS class given to each highlighted line of a code block
+pub const HIGHLIGHTED_LINE_CLASS: &str = "highlighted";
+
+/// The style of CSS classes emitted when highlighting with classes
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed {
+	prefix: CLASS_PREFIX,
+};
+
+#[derive(Eq, PartialEq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// How highlighted code is styled
+pub enum HighlightMode {
+	/// Colours are written into each token as inline styles
+	#[default]
+	Inline,
+	/// Tokens are given CSS classes, styled by a generated stylesheet
+	Classes,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+/// Syntax highlighting configuration, from the `highlighting` key of the global file
+pub struct Highlighting {
+	/// Whether code is styled inline or with CSS classes
+	pub mode: HighlightMode,
+	/// The name of the theme used to highlight code
+	pub theme: String,
+	/// The name of the theme used when the reader prefers a dark colour scheme; only used when highlighting with classes
+	pub dark_theme: Option<String>,
+	/// Path to a directory of `.sublime-syntax` files to load in addition to the default syntaxes
+	pub syntaxes: String,
+	/// Path to a directory of `.tmTheme` files to load in addition to the default themes
+	pub themes: String,
+	/// The output path of the generated stylesheet; only used when highlighting with classes
+	pub stylesheet: String,
+	/// Whether code blocks are given line numbers when their info string does not say otherwise
+	pub line_numbers: bool,
+	/// The CSS class given to code blocks, for scripts adding a button to copy their code
+	pub copy_class: String,
+}
+
+/// The initial state of a `Highlighting` object
+impl Default for Highlighting {
+	fn default() -> Self {
+		Self {
+			mode: HighlightMode::Inline,
+			theme: "InspiredGitHub".to_owned(),
+			dark_theme: None,
+			syntaxes: "./_syntaxes".to_owned(),
+			themes: "./_themes".to_owned(),
+			stylesheet: "/css/syntax.css".to_owned(),
+			line_numbers: false,
+			copy_class: "copyable".to_owned(),
+		}
+	}
+}
+
+/// A syntax highlighter, holding the syntaxes & themes loaded for a build
+pub struct Highlighter {
+	/// The highlighting configuration
+	pub options: Highlighting,
+	/// The syntaxes available for highlighting
+	pub syntax_set: SyntaxSet,
+	/// The themes available for highlighting
+	pub theme_set: ThemeSet,
+}
+
+#[derive(Eq, PartialEq, Clone, Default, Debug, Serialize, Deserialize)]
+/// Attributes given to a fenced code block in its info string, e.g. ```` ```rust {linenos=true, hl_lines="3-5", title="main.rs"} ````
+pub struct CodeBlockAttributes {
+	/// Whether each line is preceded by its number (`linenos`)
+	pub line_numbers: bool,
+	/// The number of the first line (`linenostart`)
+	pub line_number_start: usize,
+	/// The number of each line, should they not count up from `line_number_start`, such as lines included from several ranges of a file
+	pub line_number_list: Vec<usize>,
+	/// The inclusive ranges of lines to highlight, counting from one (`hl_lines`)
+	pub highlighted_lines: Vec<(usize, usize)>,
+	/// A caption shown above the code block, such as a filename (`title`)
+	pub title: Option<String>,
+}
+
+impl CodeBlockAttributes {
+	/// Parses the attributes in the remainder of a code block's info string
+	///
+	/// # Arguments
+	///
+	/// * `meta` - The info string following the language, e.g. `{linenos=true, hl_lines="3-5"}`
+	///
+	/// * `line_numbers` - Whether lines are numbered when `linenos` is not given
+	pub fn parse(meta: &str, line_numbers: bool) -> Self {
+		let mut attributes = Self {
+			line_numbers,
+			line_number_start: 1,
+			line_number_list: Vec::new(),
+			highlighted_lines: Vec::new(),
+			title: None,
+		};
+		let meta = meta.trim().trim_start_matches('{').trim_end_matches('}');
+		for (key, value) in parse_key_values(meta) {
+			match &key[..] {
+				"linenos" => attributes.line_numbers = !matches!(&value[..], "false" | "0" | ""),
+				"linenostart" => {
+					attributes.line_number_start = value
+						.parse()
+						.into_diagnostic()
+						.wrap_err(format!(
+							"Unable to read `linenostart` value ({value:?}) of a code block as a number."
+						))
+						.unwrap()
+				}
+				"hl_lines" => {
+					attributes.highlighted_lines = parse_line_ranges(&value)
+						.wrap_err("Unable to read `hl_lines` value of a code block.")
+						.unwrap()
+				}
+				"title" => attributes.title = Some(value),
+				_ => {}
+			}
+		}
+		attributes
+	}
+
+	/// Whether the line with the given number is highlighted
+	///
+	/// # Arguments
+	///
+	/// * `line` - The number of the line, counting from one
+	pub fn is_highlighted(&self, line: usize) -> bool {
+		self.highlighted_lines
+			.iter()
+			.any(|(start, end)| (*start..=*end).contains(&line))
+	}
+
+	/// Returns the number of a line
+	///
+	/// # Arguments
+	///
+	/// * `index` - The index of the line within the code block, counting from zero
+	pub fn line_number(&self, index: usize) -> usize {
+		self.line_number_list
+			.get(index)
+			.copied()
+			.unwrap_or(self.line_number_start + index)
+	}
+
+	/// Whether each line needs to be wrapped in its own element
+	pub fn wraps_lines(&self) -> bool {
+		self.line_numbers || !self.highlighted_lines.is_empty()
+	}
+}
+
+/// Returns the key-value pairs in a list such as `linenos=true, title="main.rs"`.
+/// Values may be quoted, and pairs may be separated by commas or whitespace; a key without a value is `true`.
+///
+/// # Arguments
+///
+/// * `text` - The list of key-value pairs
+pub fn parse_key_values(text: &str) -> Vec<(String, String)> {
+	let mut pairs = Vec::new();
+	let mut characters = text.chars().peekable();
+	loop {
+		while characters
+			.next_if(|c| c.is_whitespace() || *c == ',')
+			.is_some()
+		{}
+		let mut key = String::new();
+		while let Some(c) = characters.next_if(|c| !c.is_whitespace() && !matches!(c, '=' | ',')) {
+			key.push(c);
+		}
+		if key.is_empty() {
+			break;
+		}
+		while characters.next_if(|c| c.is_whitespace()).is_some() {}
+		let mut value = String::new();
+		if characters.next_if_eq(&'=').is_some() {
+			while characters.next_if(|c| c.is_whitespace()).is_some() {}
+			match characters.next_if(|c| matches!(c, '"' | '\'')) {
+				Some(quote) => {
+					for c in characters.by_ref() {
+						if c == quote {
+							break;
+						}
+						value.push(c);
+					}
+				}
+				None => {
+					while let Some(c) = characters.next_if(|c| !c.is_whitespace() && *c != ',') {
+						value.push(c);
+					}
+				}
+			}
+		} else {
+			value.push_str("true");
+		}
+		pairs.push((key, value));
+	}
+	pairs
+}
+
+/// Returns the inclusive ranges in a list of line numbers such as `3-5 7` or `3-5,7`
+///
+/// # Arguments
+///
+/// * `ranges` - The list of line numbers & ranges of line numbers
+pub fn parse_line_ranges(ranges: &str) -> miette::Result<Vec<(usize, usize)>> {
+	ranges
+		.split(|c: char| c.is_whitespace() || c == ',')
+		.filter(|range| !range.is_empty())
+		.map(|range| {
+			let (start, end) = range.split_once('-').unwrap_or((range, range));
+			let parse = |number: &str| -> miette::Result<usize> {
+				number.trim().parse().into_diagnostic().wrap_err(format!(
+					"Unable to read line range ({range:?}) as a number or a pair of numbers."
+				))
+			};
+			Ok((parse(start)?, parse(end)?))
+		})
+		.collect()
+}
+
+/// The initial state of a `Highlighter` object
+impl Default for Highlighter {
+	fn default() -> Self {
+		Self::new(Highlighting::default(), &MemorySource::default())
+	}
+}
+
+impl Highlighter {
+	/// Loads the default syntaxes & themes, as well as any found in the Mokk
+	///
+	/// # Arguments
+	///
+	/// * `options` - The highlighting configuration
+	///
+	/// * `source` - Where the Mokk's syntaxes & themes are read from
+	pub fn new(options: Highlighting, source: &dyn SiteSource) -> Self {
+		let mut syntax_set = SyntaxSet::load_defaults_newlines();
+		let syntax_paths = source.find(&format!("{}/**/*.sublime-syntax", options.syntaxes));
+		if !syntax_paths.is_empty() {
+			let mut syntax_set_builder = syntax_set.into_builder();
+			for path in syntax_paths {
+				let syntax = source
+					.read_to_string(&path)
+					.into_diagnostic()
+					.and_then(|text| {
+						SyntaxDefinition::load_from_str(
+							&text,
+							true,
+							path.file_stem().and_then(|name| name.to_str()),
+						)
+						.into_diagnostic()
+					})
+					.wrap_err(format!(
+						"Unable to load syntax definition from '{}'.",
+						path.display()
+					))
+					.unwrap();
+				syntax_set_builder.add(syntax);
+			}
+			syntax_set = syntax_set_builder.build();
+		}
+
+		let mut theme_set = ThemeSet::load_defaults();
+		for path in source.find(&format!("{}/**/*.tmTheme", options.themes)) {
+			let theme = source
+				.read_to_string(&path)
+				.into_diagnostic()
+				.and_then(|text| {
+					ThemeSet::load_from_reader(&mut Cursor::new(text)).into_diagnostic()
+				})
+				.wrap_err(format!("Unable to load theme from '{}'.", path.display()))
+				.unwrap();
+			let name = path
+				.file_stem()
+				.unwrap_or_default()
+				.to_string_lossy()
+				.to_string();
+			theme_set.themes.insert(name, theme);
+		}
+
+		let highlighter = Self {
+			options,
+			syntax_set,
+			theme_set,
+		};
+		highlighter.get_theme(&highlighter.options.theme);
+		if let Some(dark_theme) = &highlighter.options.dark_theme {
+			highlighter.get_theme(dark_theme);
+		}
+		highlighter
+	}
+
+	/// Returns the theme with the given name
+	///
+	/// # Arguments
+	///
+	/// * `name` - The name of the theme
+	pub fn get_theme(&self, name: &str) -> &Theme {
+		self.theme_set
+			.themes
+			.get(name)
+			.ok_or(miette!(
+				"Unable to find a highlighting theme named '{}'. The available themes are: {}.",
+				name,
+				self.theme_set
+					.themes
+					.keys()
+					.cloned()
+					.collect::<Vec<String>>()
+					.join(", ")
+			))
+			.unwrap()
+	}
+
+	/// Returns the syntax used to highlight code of a given language
+	///
+	/// # Arguments
+	///
+	/// * `lang` - The name or file extension of the language, if known
+	///
+	/// * `code` - The code to be highlighted, used to guess the language otherwise
+	pub fn find_syntax(&self, lang: Option<&str>, code: &str) -> &SyntaxReference {
+		lang.filter(|l| !l.is_empty())
+			.and_then(|l| self.syntax_set.find_syntax_by_token(l))
+			.or_else(|| self.syntax_set.find_syntax_by_first_line(code))
+			.unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
+	}
+
+	/// Returns the stylesheet for highlighted code, if highlighting with classes
+	pub fn stylesheet(&self) -> Option<String> {
+		if self.options.mode != HighlightMode::Classes {
+			return None;
+		}
+
+		let mut stylesheet =
+			css_for_theme_with_class_style(self.get_theme(&self.options.theme), CLASS_STYLE)
+				.into_diagnostic()
+				.wrap_err(format!(
+					"Unable to generate a stylesheet for the '{}' highlighting theme.",
+					self.options.theme
+				))
+				.unwrap();
+		stylesheet.push_str(&line_stylesheet(self.get_theme(&self.options.theme)));
+
+		if let Some(dark_theme) = &self.options.dark_theme {
+			let mut dark_stylesheet =
+				css_for_theme_with_class_style(self.get_theme(dark_theme), CLASS_STYLE)
+					.into_diagnostic()
+					.wrap_err(format!(
+						"Unable to generate a stylesheet for the '{dark_theme}' highlighting theme."
+					))
+					.unwrap();
+			dark_stylesheet.push_str(&line_stylesheet(self.get_theme(dark_theme)));
+			write!(
+				stylesheet,
+				"\n@media (prefers-color-scheme: dark) {{\n{dark_stylesheet}}}\n"
+			)
+			.into_diagnostic()
+			.wrap_err("Unable to write the dark highlighting theme to the stylesheet.")
+			.unwrap();
+		}
+
+		Some(stylesheet)
+	}
+
+	/// Returns each line of a piece of code as highlighted HTML.
+	/// Every line is self-contained; no element opened on one line is closed on another.
+	///
+	/// # Arguments
+	///
+	/// * `code` - The code to highlight
+	///
+	/// * `lang` - The language of the code, if known
+	pub fn highlight_lines(&self, code: &str, lang: Option<&str>) -> Vec<String> {
+		let syntax = self.find_syntax(lang, code);
+		match self.options.mode {
+			HighlightMode::Inline => self.highlight_lines_inline(code, syntax),
+			HighlightMode::Classes => self.highlight_lines_classed(code, syntax),
+		}
+		.unwrap_or_else(|_| LinesWithEndings::from(code).map(escape_html).collect())
+	}
+
+	/// Returns a piece of code as highlighted HTML
+	///
+	/// # Arguments
+	///
+	/// * `code` - The code to highlight
+	///
+	/// * `lang` - The language of the code, if known
+	pub fn highlight(&self, code: &str, lang: Option<&str>) -> String {
+		self.highlight_lines(code, lang).concat()
+	}
+
+	/// Returns a piece of code as highlighted HTML, with its lines numbered & highlighted as its attributes ask
+	///
+	/// # Arguments
+	///
+	/// * `code` - The code to highlight
+	///
+	/// * `lang` - The language of the code, if known
+	///
+	/// * `attributes` - The attributes of the code block
+	pub fn highlight_block(
+		&self,
+		code: &str,
+		lang: Option<&str>,
+		attributes: &CodeBlockAttributes,
+	) -> String {
+		if !attributes.wraps_lines() {
+			return self.highlight(code, lang);
+		}
+
+		let theme = self.get_theme(&self.options.theme);
+		let mut html = String::new();
+		for (index, mut line) in self.highlight_lines(code, lang).into_iter().enumerate() {
+			// The line break is kept outside the line's element
+			if let Some(line_break) = line.rfind('\n') {
+				line.remove(line_break);
+			}
+			let highlighted = attributes.is_highlighted(index + 1);
+			let (line_style, number_style) = match self.options.mode {
+				HighlightMode::Inline => (
+					match highlighted {
+						true => format!(
+							" style=\"{}\"",
+							highlighted_line_style(theme.settings.line_highlight)
+						),
+						false => String::new(),
+					},
+					format!(" style=\"{LINE_NUMBER_STYLE}\""),
+				),
+				HighlightMode::Classes => (String::new(), String::new()),
+			};
+			let line_class = match highlighted {
+				true => format!("{LINE_CLASS} {HIGHLIGHTED_LINE_CLASS}"),
+				false => LINE_CLASS.to_owned(),
+			};
+			write!(html, "<span class=\"{line_class}\"{line_style}>")
+				.into_diagnostic()
+				.wrap_err("Unable to write a line of highlighted code.")
+				.unwrap();
+			if attributes.line_numbers {
+				write!(
+					html,
+					"<span class=\"{LINE_NUMBER_CLASS}\" aria-hidden=\"true\"{number_style}>{}</span>",
+					attributes.line_number(index)
+				)
+				.into_diagnostic()
+				.wrap_err("Unable to write the number of a line of highlighted code.")
+				.unwrap();
+			}
+			html.push_str(&line);
+			html.push_str("</span>\n");
+		}
+		html
+	}
+
+	/// Returns a code block as HTML, including its title & enclosing `<pre>` and `<code>` elements
+	///
+	/// # Arguments
+	///
+	/// * `code` - The code to highlight
+	///
+	/// * `lang` - The language of the code, if known
+	///
+	/// * `attributes` - The attributes of the code block
+	pub fn render_code_block(
+		&self,
+		code: &str,
+		lang: Option<&str>,
+		attributes: &CodeBlockAttributes,
+	) -> String {
+		let mut pre_attributes = AHashMap::new();
+		if let Some(l) = lang {
+			pre_attributes.insert("lang".to_owned(), l.to_owned());
+		}
+		let mut html = Vec::new();
+		self.write_title(&mut html, attributes)
+			.and_then(|_| self.write_pre(&mut html, pre_attributes))
+			.and_then(|_| write_opening_tag(&mut html, "code", Vec::new()))
+			.into_diagnostic()
+			.wrap_err("Unable to write the opening tags of a code block.")
+			.unwrap();
+		let mut html = String::from_utf8_lossy(&html).to_string();
+		html.push_str(&self.highlight_block(code, lang, attributes));
+		html.push_str("</code></pre>\n");
+		html
+	}
+
+	/// Writes the title of a code block, if it has one
+	fn write_title(
+		&self,
+		output: &mut dyn Write,
+		attributes: &CodeBlockAttributes,
+	) -> io::Result<()> {
+		match &attributes.title {
+			Some(title) => write!(
+				output,
+				"<div class=\"{TITLE_CLASS}\">{}</div>",
+				escape_html(title)
+			),
+			None => Ok(()),
+		}
+	}
+
+	/// Writes the opening `<pre>` tag of a code block, styled for highlighting
+	fn write_pre(
+		&self,
+		output: &mut dyn Write,
+		mut attributes: AHashMap<String, String>,
+	) -> io::Result<()> {
+		let mut classes = vec![self.options.copy_class.clone()];
+		match self.options.mode {
+			HighlightMode::Inline => {
+				let background = self
+					.get_theme(&self.options.theme)
+					.settings
+					.background
+					.unwrap_or(Color::WHITE);
+				let style = attributes.entry("style".to_owned()).or_default();
+				style.insert_str(
+					0,
+					&format!(
+						"background-color:#{:02x}{:02x}{:02x};",
+						background.r, background.g, background.b
+					),
+				);
+			}
+			HighlightMode::Classes => classes.insert(0, format!("{CLASS_PREFIX}code")),
+		}
+		if let Some(class) = attributes.get("class") {
+			classes.push(class.to_owned());
+		}
+		attributes.insert(
+			"class".to_owned(),
+			classes
+				.into_iter()
+				.filter(|c| !c.is_empty())
+				.collect::<Vec<String>>()
+				.join(" "),
+		);
+		let mut attributes: Vec<(&String, &String)> = attributes.iter().collect();
+		attributes.sort();
+		write_opening_tag(output, "pre", attributes)
+	}
+
+	/// Highlights each line of code with inline styles
+	fn highlight_lines_inline(
+		&self,
+		code: &str,
+		syntax: &SyntaxReference,
+	) -> Result<Vec<String>, syntect::Error> {
+		let theme = self.get_theme(&self.options.theme);
+		let background = theme.settings.background.unwrap_or(Color::WHITE);
+		let mut highlighter = HighlightLines::new(syntax, theme);
+		let mut lines = Vec::new();
+		for line in LinesWithEndings::from(code) {
+			let regions = highlighter.highlight_line(line, &self.syntax_set)?;
+			let mut html = String::new();
+			append_highlighted_html_for_styled_line(
+				&regions[..],
+				IncludeBackground::IfDifferent(background),
+				&mut html,
+			)?;
+			lines.push(html);
+		}
+		Ok(lines)
+	}
+
+	/// Highlights each line of code with CSS classes, closing & reopening the scopes that span multiple lines
+	fn highlight_lines_classed(
+		&self,
+		code: &str,
+		syntax: &SyntaxReference,
+	) -> Result<Vec<String>, syntect::Error> {
+		let mut parse_state = ParseState::new(syntax);
+		let mut scope_stack = ScopeStack::new();
+		let mut lines = Vec::new();
+		for line in LinesWithEndings::from(code) {
+			let mut html = String::new();
+			for scope in scope_stack.as_slice() {
+				let classes = scope
+					.build_string()
+					.split('.')
+					.map(|atom| format!("{CLASS_PREFIX}{atom}"))
+					.collect::<Vec<String>>()
+					.join(" ");
+				write!(html, "<span class=\"{classes}\">")?;
+			}
+			let operations = parse_state.parse_line(line, &self.syntax_set)?;
+			let (spans, _) =
+				line_tokens_to_classed_spans(line, &operations, CLASS_STYLE, &mut scope_stack)?;
+			html.push_str(&spans);
+			html.push_str(&"</span>".repeat(scope_stack.len()));
+			lines.push(html);
+		}
+		Ok(lines)
+	}
+}
+
+/// The inline style given to the number of a line when highlighting with inline styles
+const LINE_NUMBER_STYLE: &str = "user-select:none;opacity:0.6;padding-right:1em;";
+
+/// Returns the style given to highlighted lines, using a theme's line highlight colour if it has one
+///
+/// # Arguments
+///
+/// * `line_highlight` - The theme's line highlight colour
+fn highlighted_line_style(line_highlight: Option<Color>) -> String {
+	let colour = match line_highlight {
+		Some(c) => format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b),
+		None => "rgba(255,255,0,0.15)".to_owned(),
+	};
+	format!("background-color:{colour};display:inline-block;width:100%;")
+}
+
+/// Returns the stylesheet rules for numbered & highlighted lines of code
+///
+/// # Arguments
+///
+/// * `theme` - The theme styling the code
+fn line_stylesheet(theme: &Theme) -> String {
+	format!(
+		"\n.{CLASS_PREFIX}code .{LINE_NUMBER_CLASS} {{\n {}\n}}\n.{CLASS_PREFIX}code .{HIGHLIGHTED_LINE_CLASS} {{\n {}\n}}\n",
+		LINE_NUMBER_STYLE.replace(';', "; ").trim_end(),
+		highlighted_line_style(theme.settings.line_highlight)
+			.replace(';', "; ")
+			.trim_end()
+	)
+}
+
+/// Writes an opening HTML tag with the given attributes
+///
+/// # Arguments
+///
+/// * `output` - Where to write the tag
+///
+/// * `tag` - The name of the tag
+///
+/// * `attributes` - The tag's attributes
+pub fn write_opening_tag<'a>(
+	output: &mut dyn Write,
+	tag: &str,
+	attributes: impl IntoIterator<Item = (&'a String, &'a String)>,
+) -> io::Result<()> {
+	write!(output, "<{tag}")?;
+	for (attribute, value) in attributes {
+		write!(output, " {}=\"{}\"", attribute, escape_html(value))?;
+	}
+	write!(output, ">")
+}
+
+/// Renders the code blocks of a Markdown document as highlighted HTML, with the attributes given in their info strings
+///
+/// # Arguments
+///
+/// * `root` - The root node of the Markdown document
+///
+/// * `highlighter` - The syntax highlighter
+pub fn render_code_blocks<'a>(root: &'a AstNode<'a>, highlighter: &Highlighter) {
+	for node in root.descendants() {
+		let html = match node.data.borrow().value {
+			NodeValue::CodeBlock(ref code_block) => {
+				let (lang, meta) = code_block
+					.info
+					.trim()
+					.split_once(char::is_whitespace)
+					.unwrap_or((code_block.info.trim(), ""));
+				highlighter.render_code_block(
+					&code_block.literal,
+					Some(lang).filter(|l| !l.is_empty()),
+					&CodeBlockAttributes::parse(meta.trim(), highlighter.options.line_numbers),
+				)
+			}
+			_ => continue,
+		};
+		node.data.borrow_mut().value = NodeValue::HtmlBlock(NodeHtmlBlock {
+			block_type: 6,
+			literal: html,
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_line_ranges() {
+		assert_eq!(
+			parse_line_ranges("3-5 7,10-12").unwrap(),
+			vec![(3, 5), (7, 7), (10, 12)]
+		);
+		assert_eq!(parse_line_ranges("").unwrap(), vec![]);
+		assert!(parse_line_ranges("3-x").is_err());
+		assert!(parse_line_ranges("-3").is_err());
+	}
+
+	#[test]
+	fn highlights_lines_in_several_ranges() {
+		let attributes = CodeBlockAttributes::parse("{hl_lines=\"1-2 4\"}", false);
+		assert_eq!(
+			(1..=5)
+				.map(|line| attributes.is_highlighted(line))
+				.collect::<Vec<_>>(),
+			vec![true, true, false, true, false]
+		);
+	}
+
+	#[test]
+	fn numbers_lines_from_a_list() {
+		let attributes = CodeBlockAttributes {
+			line_number_list: vec![3, 4, 10],
+			..Default::default()
+		};
+		assert_eq!(
+			(0..3)
+				.map(|index| attributes.line_number(index))
+				.collect::<Vec<_>>(),
+			vec![3, 4, 10]
+		);
+	}
+}
@@ -0,0 +1,111 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+hooks.rs - Build lifecycle hooks
+
+Programs building Mokks with Dokkoo may register functions called as a build progresses, to change pages & their HTML or to write files of their own:
+```no_run
+let mut build = dokkoo::options::BuildOptions::new("./site").build();
+build.on_page_loaded(|page| {
+	let words = page.content.split_whitespace().count();
+	page.data.insert("words".to_owned(), words.into());
+});
+build.on_page_rendered(|_page, html| *html = html.replace("TODO", "DONE"));
+build.on_collection_ready(|name, pages| vec![(format!("{name}.txt"), pages.len().to_string())]);
+build.on_build_finished(|report| println!("Built {} pages.", report.pages.len()));
+let pages = build.load_pages();
+build.build_pages(pages);
+```
+Hooks are called in the order they were registered, after those of the Mokk's scripts & plugins.
+*/
+
+use crate::report::BuildReport;
+use crate::Page;
+
+/// A function called with each page once it is loaded, before pages are indexed
+pub type PageLoadedHook = Box<dyn Fn(&mut Page) + Send + Sync>;
+/// A function called with each page & its HTML once it is compiled, before it is written
+pub type PageRenderedHook = Box<dyn Fn(&Page, &mut String) + Send + Sync>;
+/// A function called with the name & pages of each collection once every page is compiled, returning files to write to the output folder, as their paths & contents
+pub type CollectionReadyHook = Box<dyn Fn(&str, &[Page]) -> Vec<(String, String)> + Send + Sync>;
+/// A function called with the report of a build once it is finished
+pub type BuildFinishedHook = Box<dyn Fn(&BuildReport) + Send + Sync>;
+
+#[derive(Default)]
+/// The lifecycle hooks registered with a build
+pub struct Hooks {
+	/// Hooks called once each page is loaded
+	pub page_loaded: Vec<PageLoadedHook>,
+	/// Hooks called once each page is compiled
+	pub page_rendered: Vec<PageRenderedHook>,
+	/// Hooks called once each collection is complete
+	pub collection_ready: Vec<CollectionReadyHook>,
+	/// Hooks called once the build is finished
+	pub build_finished: Vec<BuildFinishedHook>,
+}
+
+impl Hooks {
+	/// Calls the hooks for a loaded page
+	///
+	/// # Arguments
+	///
+	/// * `page` - The `.mokkf` file's context as a `Page`
+	pub fn page_loaded(&self, page: &mut Page) {
+		for hook in &self.page_loaded {
+			hook(page);
+		}
+	}
+
+	/// Calls the hooks for a compiled page, returning its HTML as changed by them
+	///
+	/// # Arguments
+	///
+	/// * `page` - The `.mokkf` file's context as a `Page`
+	///
+	/// * `html` - The compiled page
+	pub fn page_rendered(&self, page: &Page, mut html: String) -> String {
+		for hook in &self.page_rendered {
+			hook(page, &mut html);
+		}
+		html
+	}
+
+	/// Calls the hooks for a complete collection, returning the files they write
+	///
+	/// # Arguments
+	///
+	/// * `name` - The name of the collection
+	///
+	/// * `pages` - The pages of the collection
+	pub fn collection_ready(&self, name: &str, pages: &[Page]) -> Vec<(String, String)> {
+		self.collection_ready
+			.iter()
+			.flat_map(|hook| hook(name, pages))
+			.collect()
+	}
+
+	/// Calls the hooks for a finished build
+	///
+	/// # Arguments
+	///
+	/// * `report` - The report of the build
+	pub fn build_finished(&self, report: &BuildReport) {
+		for hook in &self.build_finished {
+			hook(report);
+		}
+	}
+}
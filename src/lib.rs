@@ -23,23 +23,111 @@ A Mokk file represents a document or page written in accordance to [the Mokk spe
 */
 #![warn(clippy::disallowed_types)]
 
+/// Admonitions (callouts) in Markdown
+pub mod admonitions;
+/// AsciiDoc
+pub mod asciidoc;
+/// Checking the links of a built Mokk
+#[cfg(feature = "fs")]
+pub mod check;
+/// Rendering parts of pages with external commands
+#[cfg(feature = "commands")]
+pub mod commands;
+/// The C API of Dokkoo
+#[cfg(feature = "fs")]
+pub mod ffi;
+/// Access to the files of a Mokk
+pub mod files;
+/// Markup formats of Mokk files
+pub mod formats;
+/// Syntax highlighting of code
+pub mod highlight;
+/// Functions called as a build progresses
+pub mod hooks;
+/// Links between pages
+pub mod links;
+/// LaTeX math
+pub mod math;
+/// Jupyter notebooks
+pub mod notebook;
+/// Org-mode
+pub mod org;
+/// The configuration of a build
+pub mod options;
+/// The stages of rendering
+pub mod pipeline;
+/// WebAssembly plugins, providing Liquid filters, transforming HTML & generating pages
+#[cfg(feature = "plugins")]
+pub mod plugins;
+/// The Python API of Dokkoo, for scripting builds
+#[cfg(feature = "python")]
+pub mod python;
+/// Reports of builds
+pub mod report;
+/// Sanitising HTML rendered from untrusted Mokk files
+pub mod sanitize;
+/// Scanning of Markdown & HTML outside of code
+pub mod scan;
+/// Site-local scripts, registering Liquid filters & tags and hooking into builds
+#[cfg(feature = "scripts")]
+pub mod scripts;
+/// Liquid tags provided by Dokkoo
+pub mod tags;
+/// Tables of contents
+pub mod toc;
+/// The JavaScript API of Dokkoo, for previewing pages in the browser
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use admonitions::Admonitions;
 use ahash::AHashMap;
+use asciidoc::AsciiDocRenderer;
 use chrono::{DateTime, Utc};
-use comrak::plugins::syntect::SyntectAdapter;
-use comrak::{markdown_to_html_with_plugins, ComrakPlugins, ListStyleType};
+use comrak::{format_html_with_plugins, parse_document, Arena, ComrakPlugins, ListStyleType};
 use derive_more::{Constructor, Div, Error, From, Into, Mul, Rem, Shl, Shr};
-use html_minifier::HTMLMinifier;
+use files::{default_source, SiteSource};
+use formats::{format_from_extension, ContentRenderer};
+use highlight::{Highlighter, Highlighting};
+use hooks::Hooks;
 use liquid::*;
-use miette::{miette, IntoDiagnostic, WrapErr};
+use liquid_core::{ParseFilter, ParseTag};
+use links::{
+	replace_wiki_links, split_url_suffix, LinkOptions, PageReference, WikiIndex,
+	BROKEN_LINK_CLASS, WIKI_LINK_CLASS,
+};
+use miette::{miette, IntoDiagnostic, Severity, WrapErr};
+use notebook::{split_notebook, NotebookRenderer};
+use options::BuildOptions;
+use org::OrgRenderer;
+use pipeline::{default_stages, RenderContext, RenderStage};
+#[cfg(feature = "plugins")]
+use plugins::Plugins;
 use relative_path::RelativePath;
+use report::{BuildReport, ErrorReport};
+#[cfg(feature = "fs")]
+use report::{FileReport, PageReport};
+use sanitize::SanitizeOptions;
+#[cfg(feature = "scripts")]
+use scripts::Scripts;
 use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::cell::Cell;
 use std::convert::TryFrom;
 use std::ffi::OsString;
 use std::fmt;
 use std::fmt::Write;
+#[cfg(feature = "fs")]
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once};
 use sys_locale::get_locale;
+use tags::{CodeTag, Dependencies, EqrefTag};
+use toc::{apply_custom_ids, HeadingCollector, HeadingOptions, Toc};
+
+thread_local! {
+	/// Whether errors raised on this thread are being caught, and so are not printed by the panic hook
+	static CATCHING_ERRORS: Cell<bool> = const { Cell::new(false) };
+}
 
 #[derive(
 	Eq,
@@ -227,14 +315,33 @@ pub struct Page {
 	pub directory: String,
 	/// The Mokk file's base filename
 	pub name: String,
+	/// The Mokk file's extension; `mokkf`, or `ipynb` for Jupyter notebooks
+	pub extension: String,
 	/// The output path of a file; a processed `permalink` value
 	pub url: String,
 	/// Whether a Mokk file's contents are intended to be processed as Markdown or not
 	pub markdown: bool,
+	/// The markup format of a Mokk file's contents, such as `markdown`, `html` or `org`
+	pub format: String,
 	/// Whether a Mokk file's contents are intended to be processed as LaTeX Math or not
 	pub math: bool,
 	/// Whether a Mokk file is intended to be minified
 	pub minify: bool,
+	/// Whether a Mokk file's rendered contents are sanitised, removing any HTML not allowlisted
+	pub sanitize: bool,
+	/// Paths to the files, other than layouts, that were read while rendering a Mokk file
+	pub dependencies: Vec<String>,
+	/// A Mokk file's table of contents, generated from the headings in its Markdown
+	pub toc: Toc,
+	/// The pages linking to a Mokk file with wiki-links
+	pub backlinks: Vec<PageReference>,
+}
+
+impl Page {
+	/// Returns the path to the Mokk file
+	pub fn path(&self) -> String {
+		format!("{}/{}.{}", self.directory, self.name, self.extension)
+	}
 }
 
 /// Handle conversion of a Page object into a string of characters
@@ -267,6 +374,20 @@ pub struct Global {
 	pub date: Date,
 	/// Whether the build's outputs are intended to be minified
 	pub minify: bool,
+	/// Whether pages marked as drafts are built
+	pub drafts: bool,
+	/// The URL the Mokk is served from, which links between pages begin with
+	pub base_url: String,
+	/// The syntax highlighting configuration
+	pub highlighting: Highlighting,
+	/// The configuration of heading IDs & anchors
+	pub headings: HeadingOptions,
+	/// The configuration of admonitions
+	pub admonitions: Admonitions,
+	/// The configuration of links between pages
+	pub links: LinkOptions,
+	/// The configuration of HTML sanitisation
+	pub sanitize: SanitizeOptions,
 }
 
 /// The initial state of a `Global` object
@@ -276,6 +397,13 @@ impl Default for Global {
 			locale: default_locale_string(),
 			date: Date::default(),
 			minify: false,
+			drafts: false,
+			base_url: String::new(),
+			highlighting: Highlighting::default(),
+			headings: HeadingOptions::default(),
+			admonitions: Admonitions::default(),
+			links: LinkOptions::default(),
+			sanitize: SanitizeOptions::default(),
 		}
 	}
 }
@@ -297,39 +425,197 @@ pub fn locale_string_to_locale(locale: String) -> chrono::Locale {
 
 /// Data held in memory during the build process
 pub struct Build {
+	/// Path to the Mokk
+	pub root: String,
+	/// Path to the folder the Mokk is output to
+	pub output: String,
+	/// Where the files of the Mokk are read from
+	pub source: Arc<dyn SiteSource>,
 	/// A collection of pages, grouped by their collection name
 	pub collections: AHashMap<String, Vec<Page>>,
 	/// The global context, defined in the Mokk's global file
 	pub global_context: (AHashMap<String, serde_yaml::Value>, Global),
 	/// The Liquid parser
 	pub liquid_parser: liquid::Parser,
+	/// The syntax highlighter, with the syntaxes & themes loaded once per build
+	pub highlighter: Arc<Highlighter>,
+	/// The files read while rendering the current page
+	pub dependencies: Dependencies,
+	/// The paths of the Mokk files that depend on each file read while rendering
+	pub dependents: AHashMap<String, Vec<String>>,
+	/// The renderer of headings, collecting those found while rendering the current page's Markdown
+	pub heading_collector: HeadingCollector,
+	/// The pages of the Mokk, indexed for resolving wiki-links
+	pub wiki_index: WikiIndex,
+	/// The renderers of formats other than Markdown & HTML, keyed by the name of their format; a renderer registered for `markdown` replaces the built-in one
+	pub renderers: AHashMap<String, Box<dyn ContentRenderer>>,
+	/// The stages of rendering, through which text is passed in order
+	pub stages: Vec<Box<dyn RenderStage>>,
+	/// The Mokk's WebAssembly plugins
+	#[cfg(feature = "plugins")]
+	pub plugins: Plugins,
+	/// The Mokk's scripts, with the filters, tags & hooks they register
+	#[cfg(feature = "scripts")]
+	pub scripts: Scripts,
+	/// The functions called as the build progresses
+	pub hooks: Hooks,
+	/// Paths to the Mokk files of the pages last skipped when loading the Mokk, such as unpublished drafts
+	pub skipped: Vec<String>,
+	/// The errors raised while last loading the Mokk, such as those in the frontmatter of pages, which are then not built
+	pub load_errors: Vec<ErrorReport>,
+	/// The messages of the warnings raised since the Mokk was last loaded
+	pub warnings: Mutex<Vec<String>>,
 }
 
-/// The initial state of a `Build` object
+/// The initial state of a `Build` object, reading the Mokk in the current directory
 impl Default for Build {
 	fn default() -> Self {
-		Self {
-			collections: AHashMap::new(),
-			global_context: get_global_context(),
-			liquid_parser: create_liquid_parser(),
-		}
+		BuildOptions::default().build()
 	}
 }
 
 impl Build {
+	/// Creates a build of a Mokk, reading its global file & snippets
+	///
+	/// # Arguments
+	///
+	/// * `source` - Where the files of the Mokk are read from
+	pub fn new(source: Arc<dyn SiteSource>) -> Self {
+		BuildOptions::default().source(source).build()
+	}
+
+	/// Creates a build of a Mokk with the given configuration, reading its global file & snippets
+	///
+	/// # Arguments
+	///
+	/// * `options` - The configuration of the build
+	pub fn from_options(mut options: BuildOptions) -> Self {
+		let source = match options.source {
+			Some(source) => source,
+			None => {
+				// Pages are found by their paths from the Mokk, so the Mokk's folder is given as an absolute path
+				if let Ok(root) = std::path::absolute(&options.root) {
+					options.root = path_clean::clean(root).to_string_lossy().to_string();
+				}
+				default_source(&options.root)
+			}
+		};
+		let global_context = get_global_context(source.as_ref(), &options.global);
+		let highlighter = Arc::new(Highlighter::new(
+			global_context.1.highlighting.clone(),
+			source.as_ref(),
+		));
+		let dependencies: Dependencies = Arc::new(Mutex::new(Vec::new()));
+		let heading_collector = HeadingCollector::new(global_context.1.headings.clone());
+		let mut renderers: AHashMap<String, Box<dyn ContentRenderer>> = AHashMap::new();
+		renderers.insert(
+			formats::ORG.to_owned(),
+			Box::new(OrgRenderer {
+				highlighter: highlighter.clone(),
+			}),
+		);
+		renderers.insert(
+			formats::ASCIIDOC.to_owned(),
+			Box::new(AsciiDocRenderer {
+				highlighter: highlighter.clone(),
+				admonitions: global_context.1.admonitions.clone(),
+			}),
+		);
+		renderers.insert(
+			formats::NOTEBOOK.to_owned(),
+			Box::new(NotebookRenderer {
+				highlighter: highlighter.clone(),
+				admonitions: global_context.1.admonitions.clone(),
+			}),
+		);
+		let mut filters: Vec<Box<dyn ParseFilter>> = Vec::new();
+		#[cfg(feature = "plugins")]
+		let plugins = Plugins::load(source.as_ref(), &global_context.0);
+		#[cfg(feature = "plugins")]
+		filters.extend(plugins.parse_filters());
+		#[cfg(feature = "scripts")]
+		let scripts = Scripts::load(source.clone(), &global_context.0);
+		#[cfg(feature = "scripts")]
+		filters.extend(scripts.parse_filters());
+		#[cfg(feature = "scripts")]
+		let tags = scripts.parse_tags();
+		#[cfg(not(feature = "scripts"))]
+		let tags = Vec::new();
+		// Filters provided by plugins are replaced by those registered by scripts, which are replaced by those added by library users
+		filters.extend(options.filters);
+		#[allow(unused_mut)]
+		let mut build = Self {
+			liquid_parser: create_liquid_parser(
+				source.clone(),
+				highlighter.clone(),
+				dependencies.clone(),
+				filters,
+				tags,
+			),
+			output: options
+				.output
+				.unwrap_or(format!("{}/output", options.root)),
+			root: options.root,
+			source,
+			collections: AHashMap::new(),
+			global_context,
+			highlighter,
+			dependencies,
+			dependents: AHashMap::new(),
+			heading_collector,
+			wiki_index: WikiIndex::default(),
+			renderers,
+			stages: default_stages(),
+			#[cfg(feature = "plugins")]
+			plugins,
+			#[cfg(feature = "scripts")]
+			scripts,
+			hooks: Hooks::default(),
+			skipped: Vec::new(),
+			load_errors: Vec::new(),
+			warnings: Mutex::new(Vec::new()),
+		};
+		#[cfg(feature = "commands")]
+		for (before, stage) in commands::command_stages(&build.global_context.0, &build.root) {
+			build.insert_stage_before(&before, Box::new(stage));
+		}
+		build
+	}
+
 	/// Returns an object with a `Page`'s context
 	///
 	/// # Arguments
 	///
 	/// * `page_path` - The `.mokkf` file's path as a `String`
 	pub fn get_page_object(&self, page_path: String) -> Page {
+		let page_text = self
+			.source
+			.read_to_string(Path::new(&page_path))
+			.into_diagnostic()
+			.wrap_err(format!("Failed to read the file at '{}'.", &page_path))
+			.unwrap();
+		self.get_page_object_from_text(page_path, page_text)
+	}
+
+	/// Returns an object with the context of a `Page` whose text has already been read
+	///
+	/// # Arguments
+	///
+	/// * `page_path` - The `.mokkf` file's path as a `String`
+	///
+	/// * `page_text` - The text of the `.mokkf` file, including its frontmatter
+	pub fn get_page_object_from_text(&self, page_path: String, page_text: String) -> Page {
 		// Define variables which we'll use to create our Document, which we'll use to generate the Page context
-		let split_page = split_frontmatter(
-			fs::read_to_string(&page_path)
-				.into_diagnostic()
-				.wrap_err(format!("Failed to read the file at '{}'.", &page_path))
-				.unwrap(),
-		); // See file::split_frontmatter
+		let page_extension = Path::new(&page_path)
+			.extension()
+			.unwrap_or_default()
+			.to_string_lossy()
+			.to_string();
+		// Notebooks keep their frontmatter in their metadata
+		let split_page = match format_from_extension(&page_extension) {
+			Some(formats::NOTEBOOK) => split_notebook(page_text),
+			_ => split_frontmatter(page_text),
+		}; // See file::split_frontmatter
 		let frontmatter: AHashMap<String, serde_yaml::Value> = serde_yaml::from_str(&split_page.0)
 			.into_diagnostic()
 			.wrap_err(format!(
@@ -363,6 +649,31 @@ impl Build {
 			None => true,
 		};
 
+		// The format is given in the frontmatter, or by the file's extension (or one preceding `.mokkf`), falling back on whether the page is Markdown
+		let extension_format = match page_extension.as_str() {
+			"mokkf" => Path::new(&page_path)
+				.file_stem()
+				.and_then(|stem| Path::new(stem).extension())
+				.and_then(|extension| format_from_extension(&extension.to_string_lossy())),
+			extension => format_from_extension(extension),
+		};
+		let format_value: String = match frontmatter.get("format") {
+			Some(f) => f
+				.as_str()
+				.ok_or(miette!(
+					"Unable to read `format` value ({:?}) as string in frontmatter of file '{}'.",
+					f,
+					&page_path
+				))
+				.unwrap()
+				.to_lowercase(),
+			None => match (extension_format, markdown_bool) {
+				(Some(format), _) => format.to_owned(),
+				(None, true) => formats::MARKDOWN.to_owned(),
+				(None, false) => formats::HTML.to_owned(),
+			},
+		};
+
 		let math_bool: bool = match frontmatter.get("math") {
 			Some(m) => m
 				.as_bool()
@@ -399,6 +710,31 @@ impl Build {
 			None => self.global_context.1.minify,
 		};
 
+		// Pages in sanitised collections or folders cannot opt out of sanitisation, as they are not trusted to
+		let relative_path = pathdiff::diff_paths(&page_path, &self.root)
+			.map_or(page_path.clone(), |path| path.to_string_lossy().to_string());
+		let sanitize_value = self.global_context.1.sanitize.is_sanitized(
+			frontmatter.get("collection").and_then(|c| c.as_str()),
+			&relative_path,
+		) || match frontmatter.get("sanitize") {
+			Some(s) => s
+				.as_bool()
+				.ok_or(miette!(
+					"Unable to read `sanitize` value ({:?}) from page frontmatter.",
+					s
+				))
+				.unwrap(),
+			None => false,
+		};
+		#[cfg(not(feature = "sanitize"))]
+		if sanitize_value {
+			Err::<(), _>(miette!(
+				"Unable to sanitise '{}', as Dokkoo was built without the `sanitize` feature.",
+				&page_path
+			))
+			.unwrap();
+		}
+
 		let locale: chrono::Locale = locale_string_to_locale(locale_value); // Get locale from Global context
 
 		let date_object = Date::value_to_date(frontmatter.get("date"), locale);
@@ -408,6 +744,7 @@ impl Build {
 		// Define our Page
 		let mut page = Page {
 			minify: minify_value,
+			sanitize: sanitize_value,
 			data: serde_yaml::from_str(&split_page.0)
 				.into_diagnostic()
 				.wrap_err(format!(
@@ -438,17 +775,26 @@ impl Build {
 				))
 				.unwrap()
 				.to_owned(),
+			extension: page_extension,
 			url: String::new(),
-			markdown: markdown_bool,
+			markdown: format_value == formats::MARKDOWN,
+			format: format_value,
 			math: math_bool,
+			dependencies: Vec::new(),
+			toc: Toc::default(),
+			backlinks: Vec::new(),
 		};
 
 		match &page.permalink[..] {
 			// Don't render the URL if the permalink is empty
 			"" => {}
 			_ => {
-				// Render the URL once the Page metadata has been generated
-				page.url = self.render(&page, &get_permalink(&permalink_string), false, false);
+				// Render the URL once the Page metadata has been generated; the permalinks of sanitised pages are only rendered if they are shorthand
+				let permalink = get_permalink(&permalink_string);
+				page.url = match page.sanitize && permalink == permalink_string {
+					true => permalink,
+					false => self.render(&page, &permalink, formats::HTML, false),
+				};
 			}
 		}
 
@@ -471,13 +817,13 @@ impl Build {
 			None => AHashMap::new(),
 			Some(l) => serde_yaml::from_str(
 				&split_frontmatter(
-					fs::read_to_string(format!(
+					self.source.read_to_string(Path::new(&format!(
 						"./layouts/{}.mokkf",
 						l
 							.as_str()
 							.ok_or(miette!("Unable to represent layout name ({:?}) as a string while rendering '{:#?}'.", l, page))
 							.unwrap()
-					))
+					)))
 					.into_diagnostic()
 					.wrap_err(format!("Unable to read layout file ({:?}) mentioned in frontmatter of file '{}'.", l, page.name))
 					.unwrap(),
@@ -507,80 +853,535 @@ impl Build {
 	///
 	/// * `text_to_render` - The text to be rendered
 	///
-	/// * `markdown` - Whether or not to render Markdown
+	/// * `format` - The markup format of the text, such as `markdown` or `html`
 	///
 	/// * `math` - Whether or not to render LaTeX Math
-	pub fn render(&self, page: &Page, text_to_render: &str, markdown: bool, math: bool) -> String {
-		let template = self
-			.liquid_parser
-			.parse(text_to_render)
-			.into_diagnostic()
-			.wrap_err(format!(
-				"Unable to parse text to render ('{text_to_render}') for {page:#?}."
-			))
-			.unwrap();
-
-		let mut rendered = template
-			.render(&self.get_contexts(page))
-			.into_diagnostic()
-			.wrap_err(format!(
-				"Unable to render text ('{text_to_render}') for {page:#?}."
-			))
-			.unwrap();
+	pub fn render(&self, page: &Page, text_to_render: &str, format: &str, math: bool) -> String {
+		let context = RenderContext {
+			page,
+			format,
+			math,
+			content: false,
+		};
+		self.run_stages(&context, text_to_render)
+	}
 
-		rendered = match markdown {
-			true => render_markdown(rendered, math),
-			false => rendered,
+	/// Returns a `String` with text rendered as the contents of a page, in the page's format
+	///
+	/// # Arguments
+	///
+	/// * `page` - A `.mokkf` file's context as a `Page`
+	///
+	/// * `text_to_render` - The contents to be rendered
+	pub fn render_content(&self, page: &Page, text_to_render: &str) -> String {
+		let context = RenderContext {
+			page,
+			format: &page.format,
+			math: page.math,
+			content: true,
 		};
+		self.run_stages(&context, text_to_render)
+	}
 
-		rendered = match math {
-			true => latex2mathml::replace(&rendered)
-				.into_diagnostic()
-				.wrap_err(format!(
-					"Unable to render math in document ('{rendered}') for {page:#?}."
-				))
-				.unwrap(),
-			false => rendered,
+	/// Returns text transformed by each stage of rendering in turn
+	///
+	/// # Arguments
+	///
+	/// * `context` - What is being rendered
+	///
+	/// * `text_to_render` - The text to be rendered
+	fn run_stages(&self, context: &RenderContext, text_to_render: &str) -> String {
+		self.stages
+			.iter()
+			.fold(text_to_render.to_owned(), |text, stage| {
+				stage.process(self, context, text)
+			})
+	}
+
+	/// Whether text in a markup format is a template, in which Liquid is rendered
+	///
+	/// # Arguments
+	///
+	/// * `format` - The name of the format
+	pub fn is_template(&self, format: &str) -> bool {
+		self.renderers
+			.get(format)
+			.is_none_or(|renderer| renderer.is_template())
+	}
+
+	/// Registers the renderer of a markup format, replacing any renderer already registered for it
+	///
+	/// # Arguments
+	///
+	/// * `format` - The name of the format, as given in the `format` frontmatter key
+	///
+	/// * `renderer` - The renderer of the format
+	pub fn add_renderer(&mut self, format: &str, renderer: Box<dyn ContentRenderer>) {
+		self.renderers.insert(format.to_lowercase(), renderer);
+	}
+
+	/// Adds a stage to the end of rendering
+	///
+	/// # Arguments
+	///
+	/// * `stage` - The stage of rendering to add
+	pub fn add_stage(&mut self, stage: Box<dyn RenderStage>) {
+		self.stages.push(stage);
+	}
+
+	/// Adds a stage of rendering before another, or to the end of rendering if there is no stage with that name
+	///
+	/// # Arguments
+	///
+	/// * `name` - The name of the stage to add the new stage before
+	///
+	/// * `stage` - The stage of rendering to add
+	pub fn insert_stage_before(&mut self, name: &str, stage: Box<dyn RenderStage>) {
+		let position = self
+			.stages
+			.iter()
+			.position(|s| s.name() == name)
+			.unwrap_or(self.stages.len());
+		self.stages.insert(position, stage);
+	}
+
+	/// Replaces a stage of rendering, returning the stage replaced if there was one with that name
+	///
+	/// # Arguments
+	///
+	/// * `name` - The name of the stage to replace
+	///
+	/// * `stage` - The stage of rendering to replace it with
+	pub fn replace_stage(
+		&mut self,
+		name: &str,
+		stage: Box<dyn RenderStage>,
+	) -> Option<Box<dyn RenderStage>> {
+		let position = self.stages.iter().position(|s| s.name() == name)?;
+		Some(std::mem::replace(&mut self.stages[position], stage))
+	}
+
+	/// Removes a stage of rendering, returning it if there was one with that name
+	///
+	/// # Arguments
+	///
+	/// * `name` - The name of the stage to remove
+	pub fn remove_stage(&mut self, name: &str) -> Option<Box<dyn RenderStage>> {
+		let position = self.stages.iter().position(|s| s.name() == name)?;
+		Some(self.stages.remove(position))
+	}
+
+	/// Registers a function called with each page once it is loaded, before pages are indexed
+	///
+	/// # Arguments
+	///
+	/// * `hook` - The function, which may change the page
+	pub fn on_page_loaded(&mut self, hook: impl Fn(&mut Page) + Send + Sync + 'static) {
+		self.hooks.page_loaded.push(Box::new(hook));
+	}
+
+	/// Registers a function called with each page & its HTML once it is compiled, before it is written
+	///
+	/// # Arguments
+	///
+	/// * `hook` - The function, which may change the HTML
+	pub fn on_page_rendered(&mut self, hook: impl Fn(&Page, &mut String) + Send + Sync + 'static) {
+		self.hooks.page_rendered.push(Box::new(hook));
+	}
+
+	/// Registers a function called with the name & pages of each collection once every page is built
+	///
+	/// # Arguments
+	///
+	/// * `hook` - The function, returning files to write to the output folder, as their paths (relative to the output folder) & contents
+	pub fn on_collection_ready(
+		&mut self,
+		hook: impl Fn(&str, &[Page]) -> Vec<(String, String)> + Send + Sync + 'static,
+	) {
+		self.hooks.collection_ready.push(Box::new(hook));
+	}
+
+	/// Registers a function called with the report of a build once it is finished
+	///
+	/// # Arguments
+	///
+	/// * `hook` - The function
+	pub fn on_build_finished(&mut self, hook: impl Fn(&BuildReport) + Send + Sync + 'static) {
+		self.hooks.build_finished.push(Box::new(hook));
+	}
+
+	/// Loads the pages of the Mokk, skipping layouts, snippets & unpublished drafts, and indexes them
+	pub fn load_pages(&mut self) -> Vec<Page> {
+		// Warnings raised while loading are kept for the report of the build that follows
+		self.warnings.lock().unwrap().clear();
+		let path = &self.root;
+		let mut pages: Vec<Page> = Vec::new();
+		let mut skipped: Vec<String> = Vec::new();
+		let mut errors: Vec<ErrorReport> = Vec::new();
+		for file in find_pages(self.source.as_ref(), path) {
+			let file_root = pathdiff::diff_paths(file.parent().unwrap(), path).unwrap();
+			let file_root_str = file_root.to_str().unwrap();
+			if self.source.is_dir(&file)
+				|| file_root_str.starts_with("layouts")
+				|| file_root_str.starts_with("snippets")
+				|| file_root_str.ends_with(".ipynb_checkpoints")
+			{
+				continue;
+			}
+
+			// An error loading one page does not stop the others from being loaded
+			let loaded = catch_error(|| {
+				let mut page = self.get_page_object(format!("{}", file.display()));
+				self.hooks.page_loaded(&mut page);
+				page
+			});
+			match loaded {
+				Ok(page) if self.is_published(&page) => pages.push(page),
+				Ok(page) => skipped.push(page.path()),
+				Err(message) => errors.push(ErrorReport {
+					path: file.display().to_string(),
+					message,
+				}),
+			}
+		}
+
+		// Pages generated by plugins are loaded as though they were Mokk files
+		#[cfg(feature = "plugins")]
+		let generated_pages =
+			catch_error(|| self.plugins.generate_pages(&self.global_context.0, &pages))
+				.unwrap_or_else(|message| {
+					errors.push(ErrorReport {
+						path: plugins::PLUGINS_FOLDER.to_owned(),
+						message,
+					});
+					Vec::new()
+				});
+		#[cfg(feature = "plugins")]
+		for generated in generated_pages {
+			let path = generated.path.clone();
+			let loaded = catch_error(|| {
+				let mut page = self.get_page_object_from_text(generated.path, generated.text);
+				// Pages generated by plugins are only written within the output folder
+				files::contained_path(page.url.trim_start_matches('/'))
+					.ok_or(miette!(
+						"A plugin generated the page '{}' with a permalink outside of the output folder ('{}').",
+						page.path(),
+						page.url
+					))
+					.unwrap();
+				self.hooks.page_loaded(&mut page);
+				page
+			});
+			match loaded {
+				Ok(page) if self.is_published(&page) => pages.push(page),
+				Ok(page) => skipped.push(page.path()),
+				Err(message) => errors.push(ErrorReport { path, message }),
+			}
+		}
+
+		// Every page is loaded before any are compiled, so that links between them can be resolved
+		self.index_pages(&pages);
+		self.skipped = skipped;
+		self.load_errors = errors;
+		pages
+	}
+
+	/// Returns whether a page is built: pages marked as drafts (with `draft: true`) are only built if drafts are
+	///
+	/// # Arguments
+	///
+	/// * `page` - The `.mokkf` file's context as a `Page`
+	pub fn is_published(&self, page: &Page) -> bool {
+		self.global_context.1.drafts
+			|| !page
+				.data
+				.get("draft")
+				.and_then(|d| d.as_bool())
+				.unwrap_or(false)
+	}
+
+	/// Compiles pages, writing them to the output folder along with the stylesheet for highlighted code, and returns a report of the build
+	///
+	/// Pages that raise errors while compiling are not written, and the errors are given in the report.
+	///
+	/// # Arguments
+	///
+	/// * `pages` - The pages to compile
+	#[cfg(feature = "fs")]
+	pub fn build_pages(&mut self, pages: Vec<Page>) -> BuildReport {
+		let started = std::time::Instant::now();
+		let mut report = BuildReport {
+			output: self.output.clone(),
+			skipped: self.skipped.clone(),
+			errors: self.load_errors.clone(),
+			..Default::default()
 		};
+		for page in pages {
+			let output_path = format!("{}/{}", self.output, page.url);
+			let path = page.path();
+			let url = page.url.clone();
+			let page_started = std::time::Instant::now();
+			// An error compiling one page does not stop the others from being built
+			match catch_error(|| self.compile(page)) {
+				Ok(compiled_page) => {
+					report.pages.push(PageReport {
+						path,
+						url,
+						bytes: compiled_page.len(),
+						seconds: page_started.elapsed().as_secs_f64(),
+					});
+					write_file(&output_path, compiled_page);
+				}
+				Err(message) => report.errors.push(ErrorReport { path, message }),
+			}
+		}
+
+		// Write the stylesheet for highlighted code, if highlighting with classes
+		let mut files: Vec<(String, String)> = Vec::new();
+		if let Some(stylesheet) = self.highlighter.stylesheet() {
+			files.push((self.highlighter.options.stylesheet.clone(), stylesheet));
+		}
+
+		// Write the files returned by hooks once each collection is complete, in the order of the collections' names
+		let mut collection_names: Vec<&String> = self.collections.keys().collect();
+		collection_names.sort();
+		for name in collection_names {
+			files.extend(self.hooks.collection_ready(name, &self.collections[name]));
+		}
+
+		// Write the files returned by scripts once the Mokk is built
+		#[cfg(feature = "scripts")]
+		files.extend(self.scripts.after_build(&self.collections));
+
+		for (path, text) in files {
+			report.files.push(FileReport {
+				bytes: text.len(),
+				path: path.clone(),
+			});
+			write_file(&format!("{}/{path}", self.output), text);
+		}
+
+		report.warnings = self.warnings.lock().unwrap().clone();
+		report.seconds = started.elapsed().as_secs_f64();
+		self.hooks.build_finished(&report);
+		report
+	}
+
+	/// Prints a warning, recording it for the report of the build
+	///
+	/// # Arguments
+	///
+	/// * `warning` - The warning
+	pub fn warn(&self, warning: miette::Report) {
+		eprintln!("{:?}", warning);
+		self.warnings.lock().unwrap().push(warning.to_string());
+	}
+
+	/// Indexes the pages of a Mokk, so that wiki-links between them can be resolved & their backlinks found
+	///
+	/// # Arguments
+	///
+	/// * `pages` - Every page of the Mokk
+	pub fn index_pages(&mut self, pages: &[Page]) {
+		self.wiki_index = WikiIndex::new(pages, self.source.as_ref());
+	}
 
-		match &page.minify {
+	/// Returns Markdown with its wiki-links replaced by links to the pages they refer to
+	///
+	/// # Arguments
+	///
+	/// * `page` - The `.mokkf` file's context as a `Page`
+	///
+	/// * `text_to_render` - The Markdown containing wiki-links
+	pub fn render_wiki_links(&self, page: &Page, text_to_render: &str) -> String {
+		replace_wiki_links(text_to_render, |target, label| {
+			let label = escape_html(label.unwrap_or(target));
+			// The heading linked to, such as in `[[Page#Some Heading]]`, is given by its text, and linked to by its ID
+			let headings = &self.global_context.1.headings;
+			let fragment = target
+				.split_once('#')
+				.map(|(_, heading)| format!("#{}{}", headings.prefix, headings.slugify(heading)))
+				.unwrap_or_default();
+			match self.wiki_index.resolve(target) {
+				Some(reference) => format!(
+					"<a href=\"{}\" class=\"{WIKI_LINK_CLASS}\">{label}</a>",
+					escape_html(&format!("{}{fragment}", self.page_href(page, &reference.url)))
+				),
+				// Links within the page itself, such as `[[#Some Heading]]`, need no page
+				None if target.starts_with('#') => format!(
+					"<a href=\"{}\" class=\"{WIKI_LINK_CLASS}\">{label}</a>",
+					escape_html(&fragment)
+				),
+				None => {
+					self.warn(miette!(
+						severity = Severity::Warning,
+						"Unable to find the page linked to by the wiki-link '[[{}]]' in '{}'.",
+						target,
+						page.path()
+					));
+					format!("<a class=\"{WIKI_LINK_CLASS} {BROKEN_LINK_CLASS}\">{label}</a>")
+				}
+			}
+		})
+	}
+
+	/// Returns the paths to the Mokk files indexed, keyed by the paths of their outputs
+	pub fn output_sources(&self) -> AHashMap<String, String> {
+		self.wiki_index
+			.paths
+			.values()
+			.map(|reference| {
+				(
+					reference.url.trim_start_matches('/').to_owned(),
+					reference.path.clone(),
+				)
+			})
+			.collect()
+	}
+
+	/// Returns the URL used to link to a page's output from another page, per the `links` configuration
+	///
+	/// # Arguments
+	///
+	/// * `page` - The `.mokkf` file's context as a `Page`, linking to the other page
+	///
+	/// * `url` - The output path of the page linked to
+	pub fn page_href(&self, page: &Page, url: &str) -> String {
+		let url = url.trim_start_matches('/');
+		match self.global_context.1.links.relative {
 			true => {
-				let mut html_minifier = HTMLMinifier::new();
-				html_minifier
-					.digest(&rendered)
-					.into_diagnostic()
-					.wrap_err(format!("Unable to minify HTML for {page:#?}."))
-					.unwrap();
-				String::from_utf8_lossy(html_minifier.get_html()).to_string()
+				let directory = Path::new(page.url.trim_start_matches('/'))
+					.parent()
+					.unwrap_or(Path::new(""))
+					.to_string_lossy()
+					.to_string();
+				RelativePath::new(&directory).relative(url).to_string()
 			}
-			false => rendered,
+			false => format!(
+				"{}/{url}",
+				self.global_context.1.base_url.trim_end_matches('/')
+			),
 		}
 	}
 
+	/// Returns the URL of a link or image source, rewritten to the output URL of the page if it points at a Mokk file
+	///
+	/// # Arguments
+	///
+	/// * `page` - The `.mokkf` file's context as a `Page`, containing the link
+	///
+	/// * `url` - The URL of the link or image source
+	pub fn resolve_source_link(&self, page: &Page, url: &str) -> String {
+		let (path, suffix) = split_url_suffix(url);
+		if !(path.ends_with(".mokkf") || path.ends_with(".ipynb")) || path.contains("://") {
+			return url.to_owned();
+		}
+		// Absolute paths are relative to the root of the Mokk, others to the linking Mokk file
+		let source_path = match path.strip_prefix('/') {
+			Some(path) => Path::new(".").join(path),
+			None => Path::new(&page.directory).join(path),
+		};
+		let target = self
+			.source
+			.canonicalize(&source_path)
+			.and_then(|p| {
+				self.wiki_index
+					.paths
+					.get(&p.to_string_lossy().to_string())
+			})
+			.ok_or(miette!(
+				"Unable to find the page ('{}') linked to by '{}' in '{}'.",
+				source_path.display(),
+				url,
+				page.path()
+			))
+			.unwrap();
+		format!("{}{suffix}", self.page_href(page, &target.url))
+	}
+
 	/// Compiles a Mokk file; renders, makes note of the Mokk file (when, or if, the need arises)
 	///
 	/// # Arguments
 	///
 	/// * `page` - The `.mokkf` file's context as a `Page`
 	pub fn compile(&mut self, mut page: Page) -> String {
+		#[cfg(feature = "scripts")]
+		self.scripts.before_render(&mut page, &self.collections);
 		let layout_name = &page.data.get("layout");
 		let collection_name = &page.data.get("collection");
 
+		self.dependencies.lock().unwrap().clear();
+		page.backlinks = self
+			.wiki_index
+			.backlinks
+			.get(&page.path())
+			.cloned()
+			.unwrap_or_default();
+
+		self.heading_collector.clear();
+
 		// If Page has a layout, render with layout(s)
 		// Otherwise, render with Page's contents
-		page.content = self.render(&page, &page.content, page.markdown, page.math);
-		let compiled_page = match layout_name {
+		page.content = self.render_content(&page, &page.content);
+		#[cfg(feature = "sanitize")]
+		if page.sanitize {
+			page.content = self.global_context.1.sanitize.sanitize(&page.content);
+		}
+		let toc_levels = (
+			get_toc_level(&page, "toc_min_level", 1),
+			get_toc_level(&page, "toc_max_level", 6),
+		);
+		page.toc = Toc::new(
+			&self.heading_collector.headings(),
+			toc_levels.0,
+			toc_levels.1,
+		);
+		let mut compiled_page = match layout_name {
 			None => page.content.to_owned(),
 			Some(l) => {
 				let layout_object = self.get_page_object(
 					format!("./layouts/{}.mokkf", l.as_str().ok_or(miette!("Unable to represent layout name ({:?}) as a string while rendering '{:#?}'.", l, page)).unwrap()),
 				);
 				let layouts = self.render_layouts(&page, layout_object); // Embed page in layout
-				self.render(&page, &layouts, false, false)
+				self.render(&page, &layouts, formats::HTML, false)
 				// Final render, to capture whatever layouts & snippets introduce
 			}
 		};
 
+		// Headings given IDs used elsewhere in the page, such as by its layouts, are given new IDs, along with the links to them in the page's contents & table of contents
+		if let Some(content) = self
+			.heading_collector
+			.rename_duplicate_ids(&compiled_page, &page.content)
+		{
+			let toc = Toc::new(
+				&self.heading_collector.headings(),
+				toc_levels.0,
+				toc_levels.1,
+			);
+			compiled_page = compiled_page.replacen(&page.content, &content, 1);
+			if !page.toc.html.is_empty() {
+				compiled_page = compiled_page.replacen(&page.toc.html, &toc.html, 1);
+			}
+			page.content = content;
+			page.toc = toc;
+		}
+
+		#[cfg(feature = "plugins")]
+		let compiled_page = self.plugins.transform_html(&page, compiled_page);
+		#[cfg(feature = "scripts")]
+		let compiled_page = self.scripts.after_render(&page, compiled_page);
+		let compiled_page = self.hooks.page_rendered(&page, compiled_page);
+
+		// Make note of the files read while rendering, so the page can be rebuilt when they change
+		page.dependencies = self.dependencies.lock().unwrap().clone();
+		let page_path = page.path();
+		for dependency in &page.dependencies {
+			let dependents = self.dependents.entry(dependency.to_owned()).or_default();
+			if !dependents.contains(&page_path) {
+				dependents.push(page_path.clone());
+			}
+		}
+
 		// When within a collection, append embeddable page to list of collection's entries
 		match collection_name {
 			None => {}
@@ -642,10 +1443,16 @@ impl Build {
 			name: sub.clone().name,
 			directory: sub.clone().directory,
 			permalink: sub.clone().permalink,
+			extension: sub.clone().extension,
 			url: sub.clone().url,
 			minify: sub.clone().minify,
+			sanitize: sub.clone().sanitize,
 			markdown: layout.markdown,
+			format: layout.clone().format,
 			math: layout.math,
+			dependencies: sub.clone().dependencies,
+			toc: sub.clone().toc,
+			backlinks: sub.clone().backlinks,
 		};
 
 		let super_layout = layout.data.get("layout");
@@ -662,7 +1469,7 @@ impl Build {
 				);
 				self.render_layouts(&merged_sub_page, super_layout_object)
 			}
-			None => self.render(sub, &layout.content, layout.markdown, layout.math),
+			None => self.render(sub, &layout.content, &layout.format, layout.math),
 		};
 
 		rendered
@@ -710,6 +1517,31 @@ pub fn get_permalink(permalink: &str) -> String {
     }
 }
 
+/// Returns a heading level used to limit a Mokk file's table of contents
+///
+/// # Arguments
+///
+/// * `page` - The `.mokkf` file's context as a `Page`
+///
+/// * `key` - The frontmatter key of the heading level
+///
+/// * `default` - The heading level used when the frontmatter does not give one
+pub fn get_toc_level(page: &Page, key: &str, default: u8) -> u8 {
+	match page.data.get(key) {
+		Some(l) => l
+			.as_u64()
+			.filter(|l| (1..=6).contains(l))
+			.ok_or(miette!(
+				"Unable to read `{}` value ({:?}) as a heading level from 1 to 6 in frontmatter of file '{}'.",
+				key,
+				l,
+				page.name
+			))
+			.unwrap() as u8,
+		None => default,
+	}
+}
+
 /// Returns a tuple with a Mokk file's frontmatter and contents, in that order
 ///
 /// # Arguments
@@ -749,38 +1581,56 @@ pub fn split_frontmatter(page_text: String) -> (String, String) {
 }
 
 /// Creates a Liquid parser
-pub fn create_liquid_parser() -> liquid::Parser {
+///
+/// # Arguments
+///
+/// * `source` - Where snippets, & the files included by tags, are read from
+///
+/// * `highlighter` - The syntax highlighter used by tags that include code
+///
+/// * `dependencies` - Where tags record the files they read while rendering
+///
+/// * `filters` - Liquid filters to add to those provided by Dokkoo
+///
+/// * `tags` - Liquid tags to add to those provided by Dokkoo
+pub fn create_liquid_parser(
+	source: Arc<dyn SiteSource>,
+	highlighter: Arc<Highlighter>,
+	dependencies: Dependencies,
+	filters: Vec<Box<dyn ParseFilter>>,
+	tags: Vec<Box<dyn ParseTag>>,
+) -> liquid::Parser {
 	let mut partial = liquid::partials::InMemorySource::new();
-	let snippets = glob::glob("./snippets/**/*");
-	if let Ok(s) = snippets {
-		for snippet in s {
-			let unwrapped_snippet = snippet
+	for snippet in source.find("./snippets/**/*") {
+		if !source.is_dir(&snippet) {
+			let relative_path = RelativePath::from_path(&snippet)
 				.into_diagnostic()
-				.wrap_err("Unable to interpret path to snippet file.")
+				.wrap_err(format!(
+					"Unable to interpret path to snippet file ('{}') as a relative path.",
+					snippet.display()
+				))
 				.unwrap();
-			if unwrapped_snippet.is_file() {
-				let relative_path = RelativePath::from_path(&unwrapped_snippet)
+			let snippet_name = relative_path.strip_prefix("snippets").unwrap().to_string();
+			let path = &snippet.as_path();
+			partial.add(
+				snippet_name,
+				&source
+					.read_to_string(path)
 					.into_diagnostic()
-					.wrap_err(format!(
-						"Unable to interpret path to snippet file ('{}') as a relative path.",
-						unwrapped_snippet.display()
-					))
-					.unwrap();
-				let snippet_name = relative_path.strip_prefix("snippets").unwrap().to_string();
-				let path = &unwrapped_snippet.as_path();
-				partial.add(
-					snippet_name,
-					&fs::read_to_string(path)
-						.into_diagnostic()
-						.wrap_err(format!("Unable to read snippet file '{}'.", path.display()))
-						.unwrap(),
-				);
-			}
+					.wrap_err(format!("Unable to read snippet file '{}'.", path.display()))
+					.unwrap(),
+			);
 		}
 	}
 	let partial_compiler = liquid::partials::EagerCompiler::new(partial);
-	liquid::ParserBuilder::with_stdlib()
+	let mut parser_builder = liquid::ParserBuilder::with_stdlib()
 		.tag(liquid_lib::jekyll::IncludeTag)
+		.tag(CodeTag {
+			source,
+			highlighter,
+			dependencies,
+		})
+		.tag(EqrefTag)
 		.filter(liquid_lib::jekyll::ArrayToSentenceString)
 		.filter(liquid_lib::jekyll::Pop)
 		.filter(liquid_lib::jekyll::Push)
@@ -789,7 +1639,15 @@ pub fn create_liquid_parser() -> liquid::Parser {
 		.filter(liquid_lib::jekyll::Unshift)
 		.filter(liquid_lib::shopify::Pluralize)
 		.filter(liquid_lib::extra::DateInTz)
-		.partials(partial_compiler)
+		.partials(partial_compiler);
+	// Filters added by library users replace those of the same name
+	for filter in filters {
+		parser_builder = parser_builder.filter(filter);
+	}
+	for tag in tags {
+		parser_builder = parser_builder.tag(tag);
+	}
+	parser_builder
 		.build()
 		.into_diagnostic()
 		.wrap_err("Unable to build a Liquid parser.")
@@ -803,7 +1661,42 @@ pub fn create_liquid_parser() -> liquid::Parser {
 /// * `text_to_render` - The Markdown text to render into HTML
 ///
 /// * `math` - Whether or not Markdown is being rendered with LaTeX Math
-pub fn render_markdown(text_to_render: String, math: bool) -> String {
+///
+/// * `highlighter` - The syntax highlighter used for fenced code blocks
+pub fn render_markdown(text_to_render: String, math: bool, highlighter: &Highlighter) -> String {
+	render_markdown_with_headings(
+		text_to_render,
+		math,
+		highlighter,
+		&HeadingCollector::default(),
+		&Admonitions::default(),
+		&|url| url.to_owned(),
+	)
+}
+
+/// Render Markdown as HTML, collecting the headings found while rendering
+///
+/// # Arguments
+///
+/// * `text_to_render` - The Markdown text to render into HTML
+///
+/// * `math` - Whether or not Markdown is being rendered with LaTeX Math
+///
+/// * `highlighter` - The syntax highlighter used for fenced code blocks
+///
+/// * `heading_collector` - The renderer of headings, which collects them
+///
+/// * `admonitions` - The configuration of admonitions
+///
+/// * `resolve_link` - Returns the URL a link or image source is rewritten to
+pub fn render_markdown_with_headings(
+	text_to_render: String,
+	math: bool,
+	highlighter: &Highlighter,
+	heading_collector: &HeadingCollector,
+	admonitions: &Admonitions,
+	resolve_link: &dyn Fn(&str) -> String,
+) -> String {
 	let mut options = comrak::Options::default();
 
 	options.extension.strikethrough = true;
@@ -811,8 +1704,7 @@ pub fn render_markdown(text_to_render: String, math: bool) -> String {
 	options.extension.table = true;
 	options.extension.autolink = false;
 	options.extension.tasklist = true;
-	options.extension.superscript = !math;
-	options.extension.header_ids = Some(String::from("h-"));
+	options.extension.superscript = true;
 	options.extension.footnotes = true;
 	options.extension.description_lists = true;
 	options.extension.front_matter_delimiter = None;
@@ -833,16 +1725,190 @@ pub fn render_markdown(text_to_render: String, math: bool) -> String {
 	options.render.sourcepos = false;
 
 	let mut plugins = ComrakPlugins::default();
-	let syntax_highlighting_adapter = SyntectAdapter::new("InspiredGitHub");
-	plugins.render.codefence_syntax_highlighter = Some(&syntax_highlighting_adapter);
+	plugins.render.heading_adapter = Some(heading_collector);
+
+	// Math is extracted before parsing, so that Markdown syntax within it is left alone
+	let (text_to_render, math_regions) = match math {
+		true => math::extract_math(&text_to_render),
+		false => (text_to_render, Vec::new()),
+	};
+	let text_to_render = admonitions::replace_containers(&text_to_render, admonitions);
+
+	let arena = Arena::new();
+	let root = parse_document(&arena, &text_to_render, &options);
+	admonitions::replace_blockquotes(&arena, root, admonitions);
+	links::rewrite_links(root, resolve_link);
+	highlight::render_code_blocks(root, highlighter);
+	apply_custom_ids(root, heading_collector);
+	let mut html = vec![];
+	format_html_with_plugins(root, &options, &mut html, &plugins)
+		.into_diagnostic()
+		.wrap_err("Unable to render Markdown as HTML.")
+		.unwrap();
+
+	let html = String::from_utf8_lossy(&html).to_string();
+	match math {
+		true => {
+			heading_collector.map_text(|text| math::restore_math_source(text, &math_regions));
+			math::insert_math(&html, &math_regions)
+				.into_diagnostic()
+				.wrap_err(format!("Unable to render math in document ('{html}')."))
+				.unwrap()
+		}
+		false => html,
+	}
+}
 
-	markdown_to_html_with_plugins(&text_to_render, &options, &plugins)
+/// Returns a string with the characters that are significant in HTML escaped
+///
+/// # Arguments
+///
+/// * `text` - The text to escape
+pub fn escape_html(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for character in text.chars() {
+		match character {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&#39;"),
+			_ => escaped.push(character),
+		}
+	}
+	escaped
+}
+
+/// Returns HTML with the delimiters of Liquid tags & objects escaped, so that the layouts it is embedded in do not render Liquid found within it.
+/// The contents of `<script>` & `<style>` elements are left as is, as character references are not read within them.
+///
+/// # Arguments
+///
+/// * `html` - The HTML to escape
+pub fn escape_liquid(html: &str) -> String {
+	let escape = |text: &str| text.replace("{%", "&#123;%").replace("{{", "&#123;&#123;");
+	let mut escaped = String::with_capacity(html.len());
+	let mut rest = html;
+	loop {
+		let lowercase = rest.to_ascii_lowercase();
+		let element = ["<script", "<style"]
+			.iter()
+			.filter_map(|tag| {
+				lowercase
+					.match_indices(tag)
+					.find(|(start, _)| {
+						lowercase[start + tag.len()..]
+							.starts_with(|c: char| c.is_whitespace() || c == '>')
+					})
+					.map(|(start, _)| (start, &tag[1..]))
+			})
+			.min();
+		let Some((start, name)) = element else {
+			escaped.push_str(&escape(rest));
+			return escaped;
+		};
+		let end = lowercase[start..]
+			.find(&format!("</{name}"))
+			.map_or(rest.len(), |end| start + end);
+		escaped.push_str(&escape(&rest[..start]));
+		escaped.push_str(&rest[start..end]);
+		rest = &rest[end..];
+	}
+}
+
+/// Returns the message of an error raised while building, from the payload of the panic it caused
+///
+/// # Arguments
+///
+/// * `payload` - The payload of the panic
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+	let message = payload
+		.downcast_ref::<String>()
+		.cloned()
+		.or(payload.downcast_ref::<&str>().map(|m| m.to_string()))
+		.unwrap_or("An unknown error occurred.".to_owned());
+	// Errors are raised by unwrapping them, which prefixes their messages
+	message
+		.strip_prefix("called `Result::unwrap()` on an `Err` value:")
+		.unwrap_or(&message)
+		.trim()
+		.to_owned()
+}
+
+/// Runs a function, returning the message of the error it raises instead, if it raises one.
+/// Errors caught this way are not printed by the panic hook, as they are given to the caller, such as in the report of a build.
+///
+/// # Arguments
+///
+/// * `function` - The function to run
+pub fn catch_error<T>(function: impl FnOnce() -> T) -> Result<T, String> {
+	// The panic hook is wrapped once, so that it stays quiet while errors are being caught on this thread
+	static QUIET_HOOK: Once = Once::new();
+	QUIET_HOOK.call_once(|| {
+		let hook = std::panic::take_hook();
+		std::panic::set_hook(Box::new(move |info| {
+			if !CATCHING_ERRORS.get() {
+				hook(info);
+			}
+		}));
+	});
+	let catching = CATCHING_ERRORS.replace(true);
+	let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(function));
+	CATCHING_ERRORS.set(catching);
+	result.map_err(panic_message)
+}
+
+/// Returns the paths to the pages of a Mokk (its Mokk files & Jupyter notebooks), with those in its root last
+///
+/// # Arguments
+///
+/// * `source` - Where the files of the Mokk are read from
+///
+/// * `path` - Path to the Mokk
+pub fn find_pages(source: &dyn SiteSource, path: &str) -> Vec<PathBuf> {
+	let mut root_files: Vec<PathBuf> = Vec::new();
+	let mut page_files: Vec<PathBuf> = Vec::new();
+	for extension in ["mokkf", "ipynb"] {
+		page_files.append(&mut source.find(&format!("{path}/*/*.{extension}")));
+		root_files.append(&mut source.find(&format!("{path}/*.{extension}")));
+	}
+
+	page_files.append(&mut root_files); // Make root files the last ones to compile on the list
+	page_files
+}
+
+/// Write a file to the filesystem
+///
+/// # Arguments
+///
+/// * `path` - The path to write the file to
+///
+/// * `text_to_write` - The data to write to the filesystem
+#[cfg(feature = "fs")]
+pub fn write_file(path: &str, text_to_write: String) {
+	fs::create_dir_all(Path::new(path).parent().unwrap_or(Path::new("")))
+		.into_diagnostic()
+		.wrap_err(format!("Unable to create the folder to write '{path}' in."))
+		.unwrap();
+	fs::write(path, text_to_write)
+		.into_diagnostic()
+		.wrap_err(format!("Unable to write to '{path}'."))
+		.unwrap();
 }
 
 /// Get the global context
-pub fn get_global_context() -> (AHashMap<String, serde_yaml::Value>, Global) {
-	let global_context: AHashMap<String, serde_yaml::Value> = match fs::read_to_string(
-		"./_global.yml",
+///
+/// # Arguments
+///
+/// * `source` - Where the global file is read from
+///
+/// * `overrides` - Values replacing those of the global file
+pub fn get_global_context(
+	source: &dyn SiteSource,
+	overrides: &AHashMap<String, serde_yaml::Value>,
+) -> (AHashMap<String, serde_yaml::Value>, Global) {
+	let mut global_context: AHashMap<String, serde_yaml::Value> = match source.read_to_string(
+		Path::new("./_global.yml"),
 	) {
 		Ok(g) => {
 			serde_yaml::from_str(&g)
@@ -857,6 +1923,7 @@ pub fn get_global_context() -> (AHashMap<String, serde_yaml::Value>, Global) {
 				.unwrap() // Defined as variable as it required a type annotation
 		}
 	};
+	global_context.extend(overrides.clone());
 
 	let locale_value = match global_context.get("locale") {
 		Some(l) => l
@@ -883,10 +1950,101 @@ pub fn get_global_context() -> (AHashMap<String, serde_yaml::Value>, Global) {
 		None => false,
 	};
 
+	let drafts_value = match global_context.get("drafts") {
+		Some(d) => d
+			.as_bool()
+			.ok_or(miette!(
+				"Unable to read `drafts` value ({:?}) from global file.",
+				d
+			))
+			.unwrap(),
+		None => false,
+	};
+
+	let base_url_value = match global_context.get("base_url") {
+		Some(b) => b
+			.as_str()
+			.ok_or(miette!(
+				"Unable to read `base_url` value ({:?}) from global file.",
+				b
+			))
+			.unwrap()
+			.to_owned(),
+		None => String::new(),
+	};
+
+	let highlighting_value: Highlighting = match global_context.get("highlighting") {
+		Some(h) => serde_yaml::from_value(h.clone())
+			.into_diagnostic()
+			.wrap_err(format!(
+				"Unable to read `highlighting` value ({:?}) from global file.",
+				h
+			))
+			.unwrap(),
+		None => Highlighting::default(),
+	};
+
+	let headings_value: HeadingOptions = match global_context.get("headings") {
+		Some(h) => serde_yaml::from_value(h.clone())
+			.into_diagnostic()
+			.wrap_err(format!(
+				"Unable to read `headings` value ({:?}) from global file.",
+				h
+			))
+			.unwrap(),
+		None => HeadingOptions::default(),
+	};
+
+	let admonitions_value: Admonitions = match global_context.get("admonitions") {
+		Some(a) => serde_yaml::from_value(a.clone())
+			.into_diagnostic()
+			.wrap_err(format!(
+				"Unable to read `admonitions` value ({:?}) from global file.",
+				a
+			))
+			.unwrap(),
+		None => Admonitions::default(),
+	};
+
+	let links_value: LinkOptions = match global_context.get("links") {
+		Some(l) => serde_yaml::from_value(l.clone())
+			.into_diagnostic()
+			.wrap_err(format!(
+				"Unable to read `links` value ({:?}) from global file.",
+				l
+			))
+			.unwrap(),
+		None => LinkOptions::default(),
+	};
+
+	let sanitize_value: SanitizeOptions = match global_context.get("sanitize") {
+		#[cfg(not(feature = "sanitize"))]
+		Some(_) => Err::<SanitizeOptions, _>(miette!(
+			"Unable to sanitise pages as the `sanitize` value of the global file asks, as Dokkoo was built without the `sanitize` feature."
+		))
+		.unwrap(),
+		#[cfg(feature = "sanitize")]
+		Some(s) => serde_yaml::from_value(s.clone())
+			.into_diagnostic()
+			.wrap_err(format!(
+				"Unable to read `sanitize` value ({:?}) from global file.",
+				s
+			))
+			.unwrap(),
+		None => SanitizeOptions::default(),
+	};
+
 	let global = Global {
 		locale: locale_value.clone(),
 		date: Date::chrono_to_date(Utc::now(), locale_string_to_locale(locale_value)),
 		minify: minify_value,
+		drafts: drafts_value,
+		base_url: base_url_value,
+		highlighting: highlighting_value,
+		headings: headings_value,
+		admonitions: admonitions_value,
+		links: links_value,
+		sanitize: sanitize_value,
 	};
 
 	let mut global_map: AHashMap<String, serde_yaml::Value> = serde_yaml::from_value(
@@ -0,0 +1,313 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+links.rs - Links between pages
+
+Wiki-links (`[[Page Name]]` or `[[page-name|label]]`) are resolved against every page of a Mokk by name, title or alias.
+The pages linking to each page are recorded as its backlinks.
+Links & image sources pointing at Mokk files (e.g. `../guide/install.mokkf`) are rewritten to the URLs of the pages they are rendered into.
+*/
+
+use crate::files::SiteSource;
+use crate::formats;
+use crate::scan::{replace_outside_code_blocks, skip_code_span, skip_html};
+use crate::Page;
+use ahash::AHashMap;
+use comrak::nodes::{AstNode, NodeValue};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The CSS class of wiki-links
+pub const WIKI_LINK_CLASS: &str = "wikilink";
+/// The CSS class of wiki-links whose pages could not be found
+pub const BROKEN_LINK_CLASS: &str = "broken";
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+/// Configuration of links between pages, from the `links` key of the global file
+pub struct LinkOptions {
+	/// Whether links between pages are relative to the linking page, rather than to the root of the site
+	pub relative: bool,
+	/// Path to a file listing the URL prefixes that external links may begin with, one per line; when set, `dokkoo check` reports external links not in the list
+	pub allowlist: Option<String>,
+}
+
+/// The initial state of a `LinkOptions` object
+impl Default for LinkOptions {
+	fn default() -> Self {
+		Self {
+			relative: false,
+			allowlist: None,
+		}
+	}
+}
+
+#[derive(Eq, PartialEq, Clone, Default, Debug, Serialize, Deserialize)]
+/// A reference to a page, as linked to from another
+pub struct PageReference {
+	/// Path to the Mokk file
+	pub path: String,
+	/// The Mokk file's base filename
+	pub name: String,
+	/// The page's title, if its frontmatter has one
+	pub title: String,
+	/// The output path of the page
+	pub url: String,
+}
+
+impl PageReference {
+	/// Returns a reference to a page
+	///
+	/// # Arguments
+	///
+	/// * `page` - The page to refer to
+	pub fn new(page: &Page) -> Self {
+		Self {
+			path: page.path(),
+			name: page.name.clone(),
+			title: page
+				.data
+				.get("title")
+				.and_then(|t| t.as_str())
+				.unwrap_or_default()
+				.to_owned(),
+			url: page.url.clone(),
+		}
+	}
+}
+
+/// Returns a URL split into its path & the query or fragment following it
+///
+/// # Arguments
+///
+/// * `url` - The URL to split
+pub fn split_url_suffix(url: &str) -> (&str, &str) {
+	url.split_at(url.find(['?', '#']).unwrap_or(url.len()))
+}
+
+/// Rewrites the URLs of the links & images in a Markdown document
+///
+/// # Arguments
+///
+/// * `root` - The root node of the Markdown document
+///
+/// * `rewrite` - Returns the new URL of a link or image, given its current URL
+pub fn rewrite_links<'a>(root: &'a AstNode<'a>, rewrite: &dyn Fn(&str) -> String) {
+	for node in root.descendants() {
+		if let NodeValue::Link(ref mut link) | NodeValue::Image(ref mut link) =
+			node.data.borrow_mut().value
+		{
+			link.url = rewrite(&link.url);
+		}
+	}
+}
+
+/// Returns the form of a page's name, title or alias used when resolving wiki-links, so that `Page Name` & `page-name` are the same
+///
+/// # Arguments
+///
+/// * `text` - A page's name, title or alias
+pub fn normalise_link_target(text: &str) -> String {
+	text.to_lowercase()
+		.split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+		.filter(|word| !word.is_empty())
+		.collect::<Vec<&str>>()
+		.join("-")
+}
+
+/// Returns the names a page can be linked to by: its filename, its title, and the aliases in its frontmatter (`aliases`)
+///
+/// # Arguments
+///
+/// * `page` - The page linked to
+pub fn link_names(page: &Page) -> Vec<String> {
+	let mut names = vec![page.name.clone()];
+	if let Some(title) = page.data.get("title").and_then(|t| t.as_str()) {
+		names.push(title.to_owned());
+	}
+	match page.data.get("aliases") {
+		Some(serde_yaml::Value::String(alias)) => names.push(alias.to_owned()),
+		Some(serde_yaml::Value::Sequence(aliases)) => names.extend(
+			aliases
+				.iter()
+				.filter_map(|alias| alias.as_str().map(str::to_owned)),
+		),
+		_ => {}
+	}
+	names
+}
+
+/// Returns text with its wiki-links replaced, leaving code untouched
+///
+/// # Arguments
+///
+/// * `text` - The text of a Markdown document
+///
+/// * `replace` - Returns the replacement for a wiki-link, given its target & label
+pub fn replace_wiki_links(
+	text: &str,
+	mut replace: impl FnMut(&str, Option<&str>) -> String,
+) -> String {
+	replace_outside_code_blocks(text, |prose| {
+		let bytes = prose.as_bytes();
+		let mut output = String::with_capacity(prose.len());
+		let mut copied = 0;
+		let mut i = 0;
+		while i < bytes.len() {
+			match bytes[i] {
+				b'\\' => i += 2,
+				b'`' => i = skip_code_span(prose, i),
+				b'<' => i = skip_html(prose, i),
+				b'[' if prose[i..].starts_with("[[") => {
+					let link_end = prose[i + 2..]
+						.find("]]")
+						.map(|end| i + 2 + end)
+						.filter(|end| {
+							let inner = &prose[i + 2..*end];
+							!inner.trim().is_empty() && !inner.contains(['[', ']', '\n'])
+						});
+					match link_end {
+						Some(end) => {
+							let (target, label) = match prose[i + 2..end].split_once('|') {
+								Some((target, label)) => (target.trim(), Some(label.trim())),
+								None => (prose[i + 2..end].trim(), None),
+							};
+							output.push_str(&prose[copied..i]);
+							output.push_str(&replace(target, label));
+							i = end + 2;
+							copied = i;
+						}
+						None => i += 2,
+					}
+				}
+				_ => i += 1,
+			}
+		}
+		output.push_str(&prose[copied..]);
+		output
+	})
+}
+
+#[derive(Eq, PartialEq, Clone, Default, Debug)]
+/// The pages of a Mokk, indexed for resolving links between them
+pub struct WikiIndex {
+	/// The pages, keyed by the normalised forms of their names, titles & aliases
+	pub pages: AHashMap<String, PageReference>,
+	/// The pages, keyed by the canonical paths to their Mokk files
+	pub paths: AHashMap<String, PageReference>,
+	/// The pages linking to each page, keyed by the path to the Mokk file linked to
+	pub backlinks: AHashMap<String, Vec<PageReference>>,
+}
+
+impl WikiIndex {
+	/// Indexes the pages of a Mokk, finding the wiki-links between them
+	///
+	/// # Arguments
+	///
+	/// * `pages` - Every page of the Mokk
+	///
+	/// * `source` - Where the pages were read from
+	pub fn new(pages: &[Page], source: &dyn SiteSource) -> Self {
+		let mut index = Self::default();
+		// Filenames take precedence over titles & aliases, and earlier pages over later ones
+		let mut names: Vec<(usize, String, PageReference)> = Vec::new();
+		for page in pages {
+			let reference = PageReference::new(page);
+			index.paths.insert(
+				source
+					.canonical_path(Path::new(&reference.path))
+					.to_string_lossy()
+					.to_string(),
+				reference.clone(),
+			);
+			for (position, name) in link_names(page).into_iter().enumerate() {
+				names.push((position.min(1), name, reference.clone()));
+			}
+		}
+		names.sort_by_key(|(position, _, _)| *position);
+		for (_, name, reference) in names {
+			index
+				.pages
+				.entry(normalise_link_target(&name))
+				.or_insert(reference);
+		}
+
+		// The cells of notebooks are not searched for wiki-links
+		for page in pages.iter().filter(|page| page.format != formats::NOTEBOOK) {
+			let source = PageReference::new(page);
+			let mut targets: Vec<String> = Vec::new();
+			replace_wiki_links(&page.content, |target, _label| {
+				if let Some(reference) = index.resolve(target) {
+					if reference.path != source.path && !targets.contains(&reference.path) {
+						targets.push(reference.path.clone());
+					}
+				}
+				String::new()
+			});
+			for target in targets {
+				index
+					.backlinks
+					.entry(target)
+					.or_default()
+					.push(source.clone());
+			}
+		}
+		index
+	}
+
+	/// Returns the page a wiki-link's target refers to, ignoring any fragment (`#heading`)
+	///
+	/// # Arguments
+	///
+	/// * `target` - The target of a wiki-link
+	pub fn resolve(&self, target: &str) -> Option<&PageReference> {
+		let name = target.split('#').next().unwrap_or_default();
+		self.pages.get(&normalise_link_target(name))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Renders wiki-links as their targets & labels
+	fn replace(text: &str) -> String {
+		replace_wiki_links(text, |target, label| {
+			format!("<{target}|{}>", label.unwrap_or_default())
+		})
+	}
+
+	#[test]
+	fn replaces_wiki_links() {
+		assert_eq!(
+			replace("[[Page]] and [[ Page#Some Heading | the heading ]]"),
+			"<Page|> and <Page#Some Heading|the heading>"
+		);
+	}
+
+	#[test]
+	fn leaves_code_and_html_alone() {
+		let text = "`[[Page]]` <a title=\"[[Page]]\">\\[[Page]]</a>\n```\n[[Page]]\n```\n";
+		assert_eq!(replace(text), text);
+	}
+
+	#[test]
+	fn ignores_empty_and_unclosed_links() {
+		assert_eq!(replace("[[ ]] [[Page"), "[[ ]] [[Page");
+		assert_eq!(replace("[[a\nb]]"), "[[a\nb]]");
+	}
+}
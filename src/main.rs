@@ -20,7 +20,6 @@
 	html_logo_url = "https://github.com/Dirout/dokkoo/raw/master/branding/icon.png",
 	html_favicon_url = "https://github.com/Dirout/dokkoo/raw/master/branding/icon.png"
 )]
-#![feature(panic_info_message)]
 #![warn(clippy::disallowed_types)]
 
 use actix_files::NamedFile;
@@ -29,14 +28,14 @@ use actix_web::{
 	HttpServer,
 };
 use clap::{arg, crate_version, value_parser, ArgMatches, Command};
-use glob::glob;
 use lazy_static::lazy_static;
 use miette::{miette, IntoDiagnostic, RgbColors, WrapErr};
 use mimalloc::MiMalloc;
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use dokkoo::options::BuildOptions;
+use dokkoo::report::BuildReport;
 use std::env;
 use std::fs;
-use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 use std::path::PathBuf;
@@ -59,6 +58,11 @@ lazy_static! {
 	.arg(arg!(-c --conditions "Prints conditions information")))
   .subcommand(Command::new("build")
 	.about("Outputs a Mokk")
+	.arg(arg!(PATH: "Path to a Mokk").required(true).value_parser(value_parser!(PathBuf)))
+	.arg(arg!(--check "Checks the links of the output once built"))
+	.arg(arg!(--report <FORMAT> "Prints a report of the build, as a table or as JSON").value_parser(["table", "json"])))
+  .subcommand(Command::new("check")
+	.about("Checks the links of a Mokk's output")
 	.arg(arg!(PATH: "Path to a Mokk").required(true).value_parser(value_parser!(PathBuf))))
   .subcommand(Command::new("serve")
 	.about("Outputs a Mokk")
@@ -74,16 +78,19 @@ async fn main() {
 	let lock = stdout.lock();
 	let mut buf_out = BufWriter::new(lock);
 
-	writeln!(
-		buf_out,
-		"
+	// Reports printed as JSON are the only output, so that they can be read by other programs
+	if report_format(&MATCHES) != Some("json") {
+		writeln!(
+			buf_out,
+			"
     Dokkoo  Copyright (C) 2020-2023  Emil Sayahi
     This program comes with ABSOLUTELY NO WARRANTY; for details type `dokkoo show -w'.
     This is free software, and you are welcome to redistribute it
     under certain conditions; type `dokkoo show -c' for details.
     "
-	)
-	.unwrap();
+		)
+		.unwrap();
+	}
 
 	miette::set_hook(Box::new(|_| {
 		Box::new(
@@ -99,9 +106,10 @@ async fn main() {
 	.unwrap();
 
 	std::panic::set_hook(Box::new(|e| {
-		println!(
+		eprintln!(
 			"{}\nDefined in: {}:{}:{}",
-			format!("{}", e.message().unwrap())
+			e.payload_as_str()
+				.unwrap_or_default()
 				.replace("called `Result::unwrap()` on an `Err` value", "Error"),
 			e.location().unwrap().file(),
 			e.location().unwrap().line(),
@@ -114,7 +122,17 @@ async fn main() {
 			show(show_matches);
 		}
 		Some(("build", build_matches)) => {
-			build(build_matches);
+			let (current_build, report) = build(build_matches);
+			// Pages that could not be loaded or compiled were not written
+			if !report.errors.is_empty() {
+				std::process::exit(1);
+			}
+			if build_matches.get_flag("check") {
+				check_output(&current_build);
+			}
+		}
+		Some(("check", check_matches)) => {
+			check(check_matches);
 		}
 		Some(("serve", serve_matches)) => {
 			let handle = tokio::runtime::Handle::current();
@@ -136,7 +154,7 @@ async fn serve_mokk(matches: &clap::ArgMatches) {
 	let stderr_lock = stderr.lock();
 	let mut buf_stderr = BufWriter::new(stderr_lock);
 
-	let path = env::current_dir().unwrap();
+	let path = mokk_path(matches);
 	let path_str = path.to_str().unwrap();
 	let port = matches
 		.get_one::<usize>("PORT")
@@ -148,7 +166,7 @@ async fn serve_mokk(matches: &clap::ArgMatches) {
 		path.to_str().unwrap()
 	);
 
-	let mut current_build = build(matches);
+	let (mut current_build, _) = build(matches);
 
 	let (sender, receiver) = channel(); // Open a channel to receive notifications
 	let mut watcher = RecommendedWatcher::new(sender, Config::default()).unwrap(); // Create a watcher
@@ -173,15 +191,33 @@ async fn serve_mokk(matches: &clap::ArgMatches) {
 				for path in paths {
 					if path.try_exists().unwrap()
 						&& path.is_file() && path.extension().is_some()
-						&& path.extension().unwrap() == "mokkf"
+						&& (path.extension().unwrap() == "mokkf"
+							|| path.extension().unwrap() == "ipynb")
 					{
 						let page = current_build.get_page_object(format!("{}", path.display()));
-						if page.url.is_empty() {
+						if page.url.is_empty() || !current_build.is_published(&page) {
 							continue;
 						}
-						let output_path = format!("{}/output/{}", path_str, page.url);
+						let output_path = format!("{}/{}", current_build.output, page.url);
 						let compile_page = current_build.compile(page);
-						write_file(&output_path, compile_page); // Create output path, write to file
+						dokkoo::write_file(&output_path, compile_page); // Create output path, write to file
+					} else if path.is_file() {
+						// Rebuild the pages which read the changed file while rendering
+						let changed_path = fs::canonicalize(path).unwrap_or(path.to_path_buf());
+						let dependents = current_build
+							.dependents
+							.get(changed_path.to_str().unwrap())
+							.cloned()
+							.unwrap_or_default();
+						for dependent in dependents {
+							let page = current_build.get_page_object(dependent);
+							if page.url.is_empty() || !current_build.is_published(&page) {
+								continue;
+							}
+							let output_path = format!("{}/{}", current_build.output, page.url);
+							let compile_page = current_build.compile(page);
+							dokkoo::write_file(&output_path, compile_page);
+						}
 					}
 				}
 			} // Compile file on receiving of notification
@@ -196,29 +232,15 @@ async fn serve_mokk(matches: &clap::ArgMatches) {
 ///
 /// * `PATH` - Path to a Mokk (required)
 async fn host(matches: &clap::ArgMatches) -> Server {
-	let path_buf_input = matches
-		.get_one::<PathBuf>("PATH")
-		.ok_or(miette!("❌ No path was given"))
-		.unwrap();
-	let path_buf = match std::fs::canonicalize(path_buf_input) {
-		Ok(p) => p,
-		Err(_) => std::env::current_dir()
-			.unwrap()
-			.join(path_clean::clean(path_buf_input.to_str().unwrap())),
-	};
-	let path = path_buf.to_str().unwrap();
-	env::set_current_dir(path)
-		.into_diagnostic()
-		.wrap_err_with(|| format!("Could not read a Mokk at {path}"))
-		.unwrap();
+	let output = format!("{}/output", mokk_path(matches).display());
 	let port = matches
 		.get_one::<usize>("PORT")
 		.ok_or(miette!("❌ No port was given"))
 		.unwrap();
-	HttpServer::new(|| match Path::new("./output/index.html").is_file() {
-		true => match Path::new("./output/404.html").is_file() {
+	HttpServer::new(move || match Path::new(&format!("{output}/index.html")).is_file() {
+		true => match Path::new(&format!("{output}/404.html")).is_file() {
 			true => actix_web::App::new().service(
-				actix_files::Files::new("/", "./output")
+				actix_files::Files::new("/", &output)
 					.prefer_utf8(true)
 					.use_hidden_files()
 					.use_etag(true)
@@ -226,19 +248,23 @@ async fn host(matches: &clap::ArgMatches) -> Server {
 					.show_files_listing()
 					.redirect_to_slash_directory()
 					.index_file("index.html")
-					.default_handler(|req: ServiceRequest| {
-						let (http_req, _payload) = req.into_parts();
-
-						async {
-							let response = NamedFile::open("./output/404.html")
-								.unwrap()
-								.into_response(&http_req);
-							Ok(ServiceResponse::new(http_req, response))
+					.default_handler({
+						let not_found = format!("{output}/404.html");
+						move |req: ServiceRequest| {
+							let (http_req, _payload) = req.into_parts();
+							let not_found = not_found.clone();
+
+							async move {
+								let response = NamedFile::open(not_found)
+									.unwrap()
+									.into_response(&http_req);
+								Ok(ServiceResponse::new(http_req, response))
+							}
 						}
 					}),
 			),
 			false => actix_web::App::new().service(
-				actix_files::Files::new("/", "./output")
+				actix_files::Files::new("/", &output)
 					.prefer_utf8(true)
 					.use_hidden_files()
 					.use_etag(true)
@@ -248,28 +274,32 @@ async fn host(matches: &clap::ArgMatches) -> Server {
 					.index_file("index.html"),
 			),
 		},
-		false => match Path::new("./output/404.html").is_file() {
+		false => match Path::new(&format!("{output}/404.html")).is_file() {
 			true => actix_web::App::new().service(
-				actix_files::Files::new("/", "./output")
+				actix_files::Files::new("/", &output)
 					.prefer_utf8(true)
 					.use_hidden_files()
 					.use_etag(true)
 					.use_last_modified(true)
 					.show_files_listing()
 					.redirect_to_slash_directory()
-					.default_handler(|req: ServiceRequest| {
-						let (http_req, _payload) = req.into_parts();
-
-						async {
-							let response = NamedFile::open("./output/404.html")
-								.unwrap()
-								.into_response(&http_req);
-							Ok(ServiceResponse::new(http_req, response))
+					.default_handler({
+						let not_found = format!("{output}/404.html");
+						move |req: ServiceRequest| {
+							let (http_req, _payload) = req.into_parts();
+							let not_found = not_found.clone();
+
+							async move {
+								let response = NamedFile::open(not_found)
+									.unwrap()
+									.into_response(&http_req);
+								Ok(ServiceResponse::new(http_req, response))
+							}
 						}
 					}),
 			),
 			false => actix_web::App::new().service(
-				actix_files::Files::new("/", "./output")
+				actix_files::Files::new("/", &output)
 					.prefer_utf8(true)
 					.use_hidden_files()
 					.use_etag(true)
@@ -284,91 +314,132 @@ async fn host(matches: &clap::ArgMatches) -> Server {
 	.run()
 }
 
-/// Outputs a Mokk
+/// Reads a Mokk, returning its path along with its pages, indexed by a new build
 ///
 /// # Arguments
 ///
 /// * `PATH` - Path to a Mokk (required)
-fn build(matches: &clap::ArgMatches) -> dokkoo::Build {
-	let stdout = std::io::stdout();
-	let lock = stdout.lock();
-	let mut buf_out = BufWriter::new(lock);
+fn load_mokk(matches: &clap::ArgMatches) -> (String, dokkoo::Build, Vec<dokkoo::Page>) {
+	let path_buf = mokk_path(matches);
+	let path = path_buf.to_str().unwrap();
+	fs::read_dir(path)
+		.into_diagnostic()
+		.wrap_err_with(|| format!("Could not read a Mokk at {path}"))
+		.unwrap();
 
+	let mut current_build = BuildOptions::new(path).build();
+	let pages = current_build.load_pages();
+
+	(path.to_owned(), current_build, pages)
+}
+
+/// Returns the path to the Mokk given to a subcommand, in its canonical form if it exists
+///
+/// # Arguments
+///
+/// * `PATH` - Path to a Mokk (required)
+fn mokk_path(matches: &clap::ArgMatches) -> PathBuf {
 	let path_buf_input = matches
 		.get_one::<PathBuf>("PATH")
 		.ok_or(miette!("❌ No path was given"))
 		.unwrap();
-	let path_buf = match std::fs::canonicalize(path_buf_input) {
+	match std::fs::canonicalize(path_buf_input) {
 		Ok(p) => p,
 		Err(_) => std::env::current_dir()
 			.unwrap()
 			.join(path_clean::clean(path_buf_input.to_str().unwrap())),
-	};
-
-	let path = path_buf.to_str().unwrap();
-
-	// Sort files into vectors of path buffers; for when we compile root files last
-	let mut root_files: Vec<PathBuf> = vec![];
-	let mut files: Vec<PathBuf> = vec![];
-
-	env::set_current_dir(path)
-		.into_diagnostic()
-		.wrap_err_with(|| format!("Could not read a Mokk at {path}"))
-		.unwrap(); // Set working directory to one passed to subcommand
-
-	for entry in glob(&format!("{path}/*/*.mokkf")).unwrap() {
-		let file = entry.unwrap();
-		files.push(file);
-	}
-
-	for entry in glob(&format!("{path}/*.mokkf")).unwrap() {
-		let file = entry.unwrap();
-		root_files.push(file);
 	}
+}
 
-	files.append(&mut root_files); // Make root files the last ones to compile on the list
-	let mut current_build = dokkoo::Build::default();
+/// Outputs a Mokk
+///
+/// # Arguments
+///
+/// * `PATH` - Path to a Mokk (required)
+///
+/// * `report` - The format to print a report of the build in, as a `table` or as `json`
+fn build(matches: &clap::ArgMatches) -> (dokkoo::Build, BuildReport) {
+	let stdout = std::io::stdout();
+	let lock = stdout.lock();
+	let mut buf_out = BufWriter::new(lock);
 
 	let mut timer = Stopwatch::start_new(); // Start the stopwatch
-	for file in files {
-		let file_root = pathdiff::diff_paths(file.parent().unwrap(), path).unwrap();
-		let file_root_str = file_root.to_str().unwrap();
-		let file_root_str_length = file_root_str.len();
-		if file.is_dir()
-			|| (file_root_str_length >= 7 && &file_root_str[0..7] == "layouts")
-			|| (file_root_str_length >= 9 && &file_root_str[0..9] == "snippets")
-		{
-			continue;
-		}
-
-		let page = current_build.get_page_object(format!("{}", file.display()));
-		let output_path = format!("{}/output/{}", path, page.url);
+	let (_, mut current_build, pages) = load_mokk(matches);
+	let report = current_build.build_pages(pages);
 
-		let compile_page = current_build.compile(page); // Compile the current Page
-
-		write_file(&output_path, compile_page); // Create output path, write to file
+	// Show how long it took to build, or the report of the build if asked for
+	timer.stop();
+	match report_format(matches) {
+		Some("json") => writeln!(buf_out, "{}", report.to_json()).unwrap(),
+		Some(_) => writeln!(buf_out, "{report}").unwrap(),
+		None => {
+			// Errors are otherwise given in the report
+			for error in &report.errors {
+				eprintln!("Error in '{}': {}", error.path, error.message);
+			}
+			writeln!(buf_out, "Built in {:.2} seconds.", timer.elapsed_s()).unwrap()
+		}
 	}
+	buf_out.flush().unwrap();
 
-	// Show how long it took to build
-	timer.stop();
-	writeln!(buf_out, "Built in {:.2} seconds.", timer.elapsed_s()).unwrap();
+	(current_build, report)
+}
 
-	current_build
+/// Returns the format a build's report is printed in, if one was given with `--report`
+///
+/// # Arguments
+///
+/// * `matches` - The arguments given to Dokkoo, or to one of its subcommands
+fn report_format(matches: &clap::ArgMatches) -> Option<&str> {
+	let matches = match matches.subcommand() {
+		Some(("build", build_matches)) => build_matches,
+		_ => matches,
+	};
+	matches
+		.try_get_one::<String>("report")
+		.ok()
+		.flatten()
+		.map(|format| format.as_str())
 }
 
-/// Write a file to the filesystem
+/// Checks the links of a Mokk's output
 ///
 /// # Arguments
 ///
-/// * `path` - The path to write the file to
+/// * `PATH` - Path to a Mokk (required)
+fn check(matches: &clap::ArgMatches) {
+	let (_, current_build, _) = load_mokk(matches);
+	check_output(&current_build);
+}
+
+/// Checks the links of a built Mokk, exiting with an error if any are broken
 ///
-/// * `text_to_write` - The data to write to the filesystem
-fn write_file(path: &str, text_to_write: String) {
-	fs::create_dir_all(Path::new(path).parent().unwrap()).unwrap(); // Create output path, write to file
-	let file = File::create(path).unwrap(); // Create file which we will write to
-	let mut buffered_writer = BufWriter::new(file); // Create a buffered writer, allowing us to modify the file we've just created
-	write!(buffered_writer, "{text_to_write}").unwrap(); // Write String to file
-	buffered_writer.flush().unwrap(); // Empty out the data in memory after we've written to the file
+/// # Arguments
+///
+/// * `current_build` - The build of the Mokk, having indexed its pages
+fn check_output(current_build: &dokkoo::Build) {
+	let allowlist = current_build
+		.global_context
+		.1
+		.links
+		.allowlist
+		.as_ref()
+		.map(|allowlist| {
+			dokkoo::check::read_allowlist(&*current_build.source, Path::new(allowlist))
+		});
+	let broken_links = dokkoo::check::check_output(
+		&*current_build.source,
+		Path::new(&current_build.output),
+		&current_build.output_sources(),
+		allowlist.as_deref(),
+		&current_build.global_context.1.base_url,
+	);
+	if let Err(e) = dokkoo::check::report_broken_links(&broken_links) {
+		eprintln!("{:?}", e);
+		std::process::exit(1);
+	}
+	// Written to standard error, so that reports printed as JSON remain the only standard output
+	eprintln!("No broken links were found.");
 }
 
 /// Shows information regarding the usage and handling of this software
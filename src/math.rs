@@ -0,0 +1,446 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+math.rs - LaTeX math
+
+Math delimited by `$...$`, `$$...$$`, `\(...\)` or `\[...\]` is extracted from a document before it is rendered, leaving placeholders behind.
+Code, HTML tags & comments are left untouched. Once the document has been rendered, the placeholders are replaced with MathML.
+Display math labelled with `\label{...}` is numbered, and can be referred to with `\eqref{...}`.
+*/
+
+use crate::escape_html;
+use crate::scan::{replace_outside_code_blocks, skip_code_span, skip_html};
+use latex2mathml::{latex_to_mathml, DisplayStyle, LatexError};
+
+/// Marks the start of a placeholder
+const PLACEHOLDER_START: char = '\u{E000}';
+/// Marks the end of a placeholder
+const PLACEHOLDER_END: char = '\u{E001}';
+/// The first character used to encode the index of a placeholder's math
+const PLACEHOLDER_INDEX: u32 = 0xF0000;
+/// The CSS class of numbered equations
+pub const EQUATION_CLASS: &str = "equation";
+/// The CSS class of equation numbers
+pub const EQUATION_NUMBER_CLASS: &str = "equation-number";
+/// The CSS class of references to equations
+pub const EQUATION_REFERENCE_CLASS: &str = "eqref";
+
+#[derive(Eq, PartialEq, Clone, Default, Debug)]
+/// A region of LaTeX math extracted from a document
+pub struct MathRegion {
+	/// The LaTeX within the delimiters
+	pub latex: String,
+	/// Whether the math is displayed as a block, rather than inline
+	pub display: bool,
+	/// The math as it appeared in the document, including its delimiters
+	pub source: String,
+	/// The label given to the equation with `\label{...}`, if any
+	pub label: Option<String>,
+	/// The number of the equation, if it is labelled
+	pub number: Option<usize>,
+	/// The label of the equation referred to, if the region is a reference (`\eqref{...}`) rather than math
+	pub reference: Option<String>,
+}
+
+impl MathRegion {
+	/// Returns the region's math as MathML
+	pub fn to_mathml(&self) -> Result<String, LatexError> {
+		latex_to_mathml(
+			self.latex.trim(),
+			match self.display {
+				true => DisplayStyle::Block,
+				false => DisplayStyle::Inline,
+			},
+		)
+	}
+
+	/// Returns the region as HTML; either numbered or unnumbered math, or a link to an equation
+	///
+	/// # Arguments
+	///
+	/// * `regions` - The math extracted from the document, used to find the equations referred to
+	pub fn to_html(&self, regions: &[MathRegion]) -> Result<String, LatexError> {
+		if let Some(reference) = &self.reference {
+			let number = regions
+				.iter()
+				.find(|region| region.label.as_ref() == Some(reference))
+				.and_then(|region| region.number)
+				.map_or("??".to_owned(), |number| number.to_string());
+			return Ok(format!(
+				"<a href=\"#{}\" class=\"{EQUATION_REFERENCE_CLASS}\">({number})</a>",
+				escape_html(reference)
+			));
+		}
+		match (&self.label, self.number) {
+			(Some(label), Some(number)) => Ok(format!(
+				"<span class=\"{EQUATION_CLASS}\" id=\"{}\">{}<span class=\"{EQUATION_NUMBER_CLASS}\">({number})</span></span>",
+				escape_html(label),
+				self.to_mathml()?
+			)),
+			_ => self.to_mathml(),
+		}
+	}
+}
+
+/// Returns LaTeX without its `\label{...}`, along with the label, if it has one
+///
+/// # Arguments
+///
+/// * `latex` - The LaTeX of an equation
+pub fn split_label(latex: &str) -> (String, Option<String>) {
+	let Some(start) = latex.find("\\label{") else {
+		return (latex.to_owned(), None);
+	};
+	let label_start = start + "\\label{".len();
+	match latex[label_start..].find('}') {
+		Some(end) => (
+			format!("{}{}", &latex[..start], &latex[label_start + end + 1..]),
+			Some(latex[label_start..label_start + end].trim().to_owned()),
+		),
+		None => (latex.to_owned(), None),
+	}
+}
+
+/// Returns the placeholder standing in for a region of math
+///
+/// # Arguments
+///
+/// * `index` - The index of the region among those extracted from the document
+fn placeholder(index: usize) -> String {
+	let index = char::from_u32(PLACEHOLDER_INDEX + index as u32).unwrap_or(PLACEHOLDER_END);
+	format!("{PLACEHOLDER_START}{index}{PLACEHOLDER_END}")
+}
+
+/// Returns the text of a document with its math replaced by placeholders, along with the math that was replaced
+///
+/// # Arguments
+///
+/// * `text` - The text of the document
+pub fn extract_math(text: &str) -> (String, Vec<MathRegion>) {
+	let mut regions = Vec::new();
+	let output = replace_outside_code_blocks(text, |prose| {
+		let mut output = String::with_capacity(prose.len());
+		extract_math_from_prose(prose, &mut output, &mut regions);
+		output
+	});
+
+	// Labelled equations are numbered in the order they appear
+	for (index, region) in regions
+		.iter_mut()
+		.filter(|region| region.display && region.label.is_some())
+		.enumerate()
+	{
+		region.number = Some(index + 1);
+	}
+
+	(output, regions)
+}
+
+/// Replaces the math in text outside of fenced code blocks with placeholders
+///
+/// # Arguments
+///
+/// * `text` - Text outside of fenced code blocks
+///
+/// * `output` - Where to write the text, with its math replaced
+///
+/// * `regions` - Where to record the math that was replaced
+fn extract_math_from_prose(text: &str, output: &mut String, regions: &mut Vec<MathRegion>) {
+	let bytes = text.as_bytes();
+	let mut copied = 0;
+	let mut i = 0;
+	while i < bytes.len() {
+		let (start, end, display) = match bytes[i] {
+			b'\\' => match bytes.get(i + 1) {
+				Some(b'(') => match text[i + 2..].find("\\)") {
+					Some(end) => (i + 2, i + 2 + end, false),
+					None => {
+						i += 2;
+						continue;
+					}
+				},
+				Some(b'[') => match text[i + 2..].find("\\]") {
+					Some(end) => (i + 2, i + 2 + end, true),
+					None => {
+						i += 2;
+						continue;
+					}
+				},
+				Some(b'e') if text[i..].starts_with("\\eqref{") => {
+					let label_start = i + "\\eqref{".len();
+					match text[label_start..].find('}') {
+						Some(end) => {
+							let region_end = label_start + end + 1;
+							output.push_str(&text[copied..i]);
+							output.push_str(&placeholder(regions.len()));
+							regions.push(MathRegion {
+								source: text[i..region_end].to_owned(),
+								reference: Some(
+									text[label_start..label_start + end].trim().to_owned(),
+								),
+								..Default::default()
+							});
+							i = region_end;
+							copied = region_end;
+						}
+						None => i += 2,
+					}
+					continue;
+				}
+				// Escaped characters, such as `\$`, are never delimiters
+				Some(_) => {
+					i += 2;
+					continue;
+				}
+				None => break,
+			},
+			b'`' => {
+				i = skip_code_span(text, i);
+				continue;
+			}
+			b'<' => {
+				i = skip_html(text, i);
+				continue;
+			}
+			b'$' if bytes.get(i + 1) == Some(&b'$') => match text[i + 2..].find("$$") {
+				Some(end) if !text[i + 2..i + 2 + end].trim().is_empty() => {
+					(i + 2, i + 2 + end, true)
+				}
+				_ => {
+					i += 2;
+					continue;
+				}
+			},
+			b'$' => match find_inline_dollar_end(text, i + 1) {
+				Some(end) => (i + 1, end, false),
+				None => {
+					i += 1;
+					continue;
+				}
+			},
+			_ => {
+				i += 1;
+				continue;
+			}
+		};
+		// Closing delimiters are as long as opening ones
+		let region_end = end + (start - i);
+		output.push_str(&text[copied..i]);
+		output.push_str(&placeholder(regions.len()));
+		let (latex, label) = match display {
+			true => split_label(&text[start..end]),
+			false => (text[start..end].to_owned(), None),
+		};
+		regions.push(MathRegion {
+			latex,
+			display,
+			source: text[i..region_end].to_owned(),
+			label,
+			..Default::default()
+		});
+		i = region_end;
+		copied = region_end;
+	}
+	output.push_str(&text[copied..]);
+}
+
+/// Returns the position of the `$` closing inline math, if the math is properly delimited.
+///
+/// The opening `$` must be followed by a non-whitespace character, and the next unescaped `$` must be preceded by one & not followed by a digit, so that amounts of money are not mistaken for math.
+///
+/// # Arguments
+///
+/// * `text` - The text containing the math
+///
+/// * `start` - The position following the opening `$`
+fn find_inline_dollar_end(text: &str, start: usize) -> Option<usize> {
+	let bytes = text.as_bytes();
+	if bytes.get(start).is_none_or(|b| b.is_ascii_whitespace()) {
+		return None;
+	}
+	let mut i = start;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'\\' => i += 2,
+			b'\n'
+				if text[i + 1..]
+					.trim_start_matches([' ', '\t', '\r'])
+					.starts_with('\n') =>
+			{
+				return None;
+			}
+			b'$' => {
+				return match !bytes[i - 1].is_ascii_whitespace()
+					&& !bytes.get(i + 1).is_some_and(|b| b.is_ascii_digit())
+				{
+					true => Some(i),
+					false => None,
+				};
+			}
+			_ => i += 1,
+		}
+	}
+	None
+}
+
+/// Returns a rendered document with its placeholders replaced by MathML & links to equations
+///
+/// # Arguments
+///
+/// * `text` - The rendered document
+///
+/// * `regions` - The math extracted from the document
+pub fn insert_math(text: &str, regions: &[MathRegion]) -> Result<String, LatexError> {
+	let mut output = String::with_capacity(text.len());
+	let mut rest = text;
+	while let Some((region, before, after)) = next_placeholder(rest, regions) {
+		output.push_str(before);
+		output.push_str(&region.to_html(regions)?);
+		rest = after;
+	}
+	output.push_str(rest);
+	Ok(output)
+}
+
+/// Returns text with its placeholders replaced by the math they stand in for, as it appeared in the document
+///
+/// # Arguments
+///
+/// * `text` - Text containing placeholders
+///
+/// * `regions` - The math extracted from the document
+pub fn restore_math_source(text: &str, regions: &[MathRegion]) -> String {
+	let mut output = String::with_capacity(text.len());
+	let mut rest = text;
+	while let Some((region, before, after)) = next_placeholder(rest, regions) {
+		output.push_str(before);
+		output.push_str(&region.source);
+		rest = after;
+	}
+	output.push_str(rest);
+	output
+}
+
+/// Finds the next placeholder in text, returning the math it stands in for, along with the text before & after it
+///
+/// # Arguments
+///
+/// * `text` - Text containing placeholders
+///
+/// * `regions` - The math extracted from the document
+fn next_placeholder<'a, 'b>(
+	text: &'a str,
+	regions: &'b [MathRegion],
+) -> Option<(&'b MathRegion, &'a str, &'a str)> {
+	let mut search_start = 0;
+	while let Some(offset) = text[search_start..].find(PLACEHOLDER_START) {
+		let start = search_start + offset;
+		let mut chars = text[start + PLACEHOLDER_START.len_utf8()..].chars();
+		if let (Some(index), Some(PLACEHOLDER_END)) = (chars.next(), chars.next()) {
+			let region = (index as u32)
+				.checked_sub(PLACEHOLDER_INDEX)
+				.and_then(|index| regions.get(index as usize));
+			if let Some(region) = region {
+				let end = start
+					+ PLACEHOLDER_START.len_utf8()
+					+ index.len_utf8()
+					+ PLACEHOLDER_END.len_utf8();
+				return Some((region, &text[..start], &text[end..]));
+			}
+		}
+		search_start = start + PLACEHOLDER_START.len_utf8();
+	}
+	None
+}
+
+/// Renders the math in a document as MathML, without rendering anything else
+///
+/// # Arguments
+///
+/// * `text` - The text of the document
+pub fn render_math(text: &str) -> Result<String, LatexError> {
+	let (text, regions) = extract_math(text);
+	insert_math(&text, &regions)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn extracts_inline_and_display_math() {
+		let (text, regions) = extract_math("a $x$ b $$y$$ c \\(z\\)");
+		assert_eq!(regions.len(), 3);
+		assert_eq!(
+			regions
+				.iter()
+				.map(|region| (region.latex.as_str(), region.display))
+				.collect::<Vec<_>>(),
+			vec![("x", false), ("y", true), ("z", false)]
+		);
+		assert_eq!(
+			restore_math_source(&text, &regions),
+			"a $x$ b $$y$$ c \\(z\\)"
+		);
+	}
+
+	#[test]
+	fn leaves_dollars_in_code_alone() {
+		let text = "`$x$` <span title=\"$y$\">$z$</span>\n```\n$w$\n```\n";
+		let (extracted, regions) = extract_math(text);
+		assert_eq!(regions.len(), 1);
+		assert_eq!(regions[0].latex, "z");
+		assert_eq!(restore_math_source(&extracted, &regions), text);
+	}
+
+	#[test]
+	fn inserts_math_as_mathml() {
+		let (text, regions) = extract_math("a $x$ b");
+		let html = insert_math(&text, &regions).unwrap();
+		assert!(html.starts_with("a <math"));
+		assert!(html.ends_with("</math> b"));
+	}
+
+	#[test]
+	fn numbers_labelled_equations() {
+		let (text, regions) =
+			extract_math("$$x \\label{first}$$ $$y$$ $$z \\label{second}$$ \\eqref{second}");
+		assert_eq!(
+			regions
+				.iter()
+				.map(|region| region.number)
+				.collect::<Vec<_>>(),
+			vec![Some(1), None, Some(2), None]
+		);
+		let html = insert_math(&text, &regions).unwrap();
+		assert!(html.contains("id=\"second\""));
+		assert!(html.contains("<a href=\"#second\" class=\"eqref\">(2)</a>"));
+	}
+
+	#[test]
+	fn splits_labels() {
+		assert_eq!(
+			split_label("x = 1 \\label{ eq:one }"),
+			("x = 1 ".to_owned(), Some("eq:one".to_owned()))
+		);
+		assert_eq!(split_label("x = 1"), ("x = 1".to_owned(), None));
+		assert_eq!(
+			split_label("x \\label{one"),
+			("x \\label{one".to_owned(), None)
+		);
+	}
+}
@@ -0,0 +1,277 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+notebook.rs - Jupyter notebooks
+
+Jupyter notebooks (`.ipynb`) are pages whose frontmatter is the `mokk` object of the notebook's metadata.
+Markdown cells are rendered as Markdown, code cells are highlighted, and the outputs stored with code cells (text, HTML & images) are embedded after them.
+Notebooks are not Liquid templates; their cells are rendered as they are.
+*/
+
+use crate::admonitions::Admonitions;
+use crate::escape_html;
+use crate::escape_liquid;
+use crate::formats::ContentRenderer;
+use crate::highlight::{CodeBlockAttributes, Highlighter};
+use crate::render_markdown_with_headings;
+use crate::toc::HeadingCollector;
+use miette::{miette, IntoDiagnostic, WrapErr};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// The key of a notebook's metadata holding its frontmatter
+pub const FRONTMATTER_KEY: &str = "mokk";
+
+/// Splits a notebook into its frontmatter, taken from its metadata, and the notebook itself
+///
+/// # Arguments
+///
+/// * `notebook_text` - The JSON of a Jupyter notebook
+pub fn split_notebook(notebook_text: String) -> (String, String) {
+	let notebook: Value = serde_json::from_str(&notebook_text)
+		.into_diagnostic()
+		.wrap_err("Unable to parse notebook as JSON.")
+		.unwrap();
+	let frontmatter = match notebook
+		.get("metadata")
+		.and_then(|metadata| metadata.get(FRONTMATTER_KEY))
+	{
+		Some(frontmatter) => serde_yaml::to_string(frontmatter)
+			.into_diagnostic()
+			.wrap_err(format!(
+				"Unable to read `{FRONTMATTER_KEY}` value ({frontmatter:?}) from notebook metadata."
+			))
+			.unwrap(),
+		None => "empty: true".to_owned(),
+	};
+	(frontmatter, notebook_text)
+}
+
+/// Returns the text of a notebook field, which is either a string or a list of lines
+///
+/// # Arguments
+///
+/// * `value` - The field's value
+fn multiline_text(value: Option<&Value>) -> String {
+	match value {
+		Some(Value::String(text)) => text.to_owned(),
+		Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+		_ => String::new(),
+	}
+}
+
+/// Returns text with its ANSI escape sequences, such as terminal colours in tracebacks, removed
+///
+/// # Arguments
+///
+/// * `text` - The text to strip
+fn strip_ansi(text: &str) -> String {
+	let mut stripped = String::with_capacity(text.len());
+	let mut chars = text.chars();
+	while let Some(c) = chars.next() {
+		match c {
+			'\u{1b}' => {
+				if chars.next() == Some('[') {
+					for c in chars.by_ref() {
+						if c.is_ascii_alphabetic() {
+							break;
+						}
+					}
+				}
+			}
+			c => stripped.push(c),
+		}
+	}
+	stripped
+}
+
+/// Renders Jupyter notebooks
+pub struct NotebookRenderer {
+	/// The syntax highlighter, used for code cells
+	pub highlighter: Arc<Highlighter>,
+	/// The configuration of admonitions in Markdown cells
+	pub admonitions: Admonitions,
+}
+
+impl NotebookRenderer {
+	/// Renders an output stored with a code cell as HTML
+	///
+	/// # Arguments
+	///
+	/// * `output` - The output, as stored in the notebook
+	///
+	/// * `heading_collector` - Collects the headings of Markdown outputs
+	///
+	/// * `math` - Whether or not to render LaTeX math in Markdown outputs
+	fn render_output(
+		&self,
+		output: &Value,
+		heading_collector: &HeadingCollector,
+		math: bool,
+	) -> String {
+		match output.get("output_type").and_then(Value::as_str) {
+			Some("stream") => format!(
+				"<pre class=\"output {}\">{}</pre>\n",
+				escape_html(
+					output
+						.get("name")
+						.and_then(Value::as_str)
+						.unwrap_or("stdout")
+				),
+				escape_html(&strip_ansi(&multiline_text(output.get("text"))))
+			),
+			Some("error") => format!(
+				"<pre class=\"output error\">{}</pre>\n",
+				escape_html(&strip_ansi(
+					&output
+						.get("traceback")
+						.and_then(Value::as_array)
+						.map(|lines| {
+							lines
+								.iter()
+								.filter_map(Value::as_str)
+								.collect::<Vec<&str>>()
+								.join("\n")
+						})
+						.unwrap_or_default()
+				))
+			),
+			Some("execute_result") | Some("display_data") => {
+				let data = output.get("data");
+				let field = |mime: &str| {
+					data.and_then(|d| d.get(mime))
+						.map(|v| multiline_text(Some(v)))
+				};
+				// The richest representation of the output is embedded
+				if let Some(html) = field("text/html") {
+					format!("<div class=\"output html\">\n{html}\n</div>\n")
+				} else if let Some(svg) = field("image/svg+xml") {
+					format!("<div class=\"output image\">\n{svg}\n</div>\n")
+				} else if let Some((mime, image)) = ["image/png", "image/jpeg", "image/gif"]
+					.iter()
+					.find_map(|mime| field(mime).map(|image| (mime, image)))
+				{
+					let image: String = image.split_whitespace().collect();
+					format!("<div class=\"output image\"><img src=\"data:{mime};base64,{image}\" /></div>\n")
+				} else if let Some(markdown) = field("text/markdown") {
+					format!(
+						"<div class=\"output markdown\">\n{}</div>\n",
+						self.render_markdown(markdown, heading_collector, math)
+					)
+				} else if let Some(text) = field("text/plain") {
+					format!(
+						"<pre class=\"output text\">{}</pre>\n",
+						escape_html(&strip_ansi(&text))
+					)
+				} else {
+					String::new()
+				}
+			}
+			_ => String::new(),
+		}
+	}
+
+	/// Renders Markdown from a notebook, including its LaTeX math, as Jupyter does
+	///
+	/// # Arguments
+	///
+	/// * `markdown` - The Markdown to render
+	///
+	/// * `heading_collector` - Collects the headings of the Markdown
+	///
+	/// * `math` - Whether or not to render LaTeX math
+	fn render_markdown(
+		&self,
+		markdown: String,
+		heading_collector: &HeadingCollector,
+		math: bool,
+	) -> String {
+		render_markdown_with_headings(
+			markdown,
+			math,
+			&self.highlighter,
+			heading_collector,
+			&self.admonitions,
+			&|url| url.to_owned(),
+		)
+	}
+}
+
+impl ContentRenderer for NotebookRenderer {
+	fn render(&self, text: &str, heading_collector: &HeadingCollector, math: bool) -> String {
+		let notebook: Value = serde_json::from_str(text)
+			.into_diagnostic()
+			.wrap_err("Unable to parse notebook as JSON.")
+			.unwrap();
+		let metadata = notebook.get("metadata");
+		let lang = metadata
+			.and_then(|m| m.pointer("/language_info/name"))
+			.or(metadata.and_then(|m| m.pointer("/kernelspec/language")))
+			.and_then(Value::as_str);
+		let cells = notebook
+			.get("cells")
+			.and_then(Value::as_array)
+			.ok_or(miette!("Unable to find the cells of a notebook."))
+			.unwrap();
+
+		let mut html = String::new();
+		for cell in cells {
+			let source = multiline_text(cell.get("source"));
+			match cell.get("cell_type").and_then(Value::as_str) {
+				Some("markdown") => html.push_str(&format!(
+					"<div class=\"cell markdown\">\n{}</div>\n",
+					self.render_markdown(source, heading_collector, math)
+				)),
+				Some("code") => {
+					let mut source = source;
+					if !source.ends_with('\n') {
+						source.push('\n');
+					}
+					html.push_str(&format!(
+						"<div class=\"cell code\">\n<div class=\"input\">\n{}</div>\n",
+						self.highlighter.render_code_block(
+							&source,
+							lang,
+							&CodeBlockAttributes::parse("", self.highlighter.options.line_numbers)
+						)
+					));
+					let outputs: String = cell
+						.get("outputs")
+						.and_then(Value::as_array)
+						.map(|outputs| {
+							outputs
+								.iter()
+								.map(|output| self.render_output(output, heading_collector, math))
+								.collect()
+						})
+						.unwrap_or_default();
+					if !outputs.is_empty() {
+						html.push_str(&format!("<div class=\"outputs\">\n{outputs}</div>\n"));
+					}
+					html.push_str("</div>\n");
+				}
+				// Raw cells are meant for other tools, such as nbconvert
+				_ => {}
+			}
+		}
+		escape_liquid(&html)
+	}
+
+	fn is_template(&self) -> bool {
+		false
+	}
+}
@@ -0,0 +1,160 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+options.rs - The configuration of a build
+
+Builds are configured by the Mokk's global file (`_global.yml`), but may also be configured programmatically, such as by tooling driving Dokkoo.
+Values given to a build's options take precedence over those of the global file.
+*/
+
+use crate::files::SiteSource;
+use crate::Build;
+use ahash::AHashMap;
+use liquid_core::ParseFilter;
+use std::sync::Arc;
+
+/// The configuration of a build, set programmatically rather than in the global file
+///
+/// ```no_run
+/// let mut build = dokkoo::options::BuildOptions::new("./site")
+///     .output("./public")
+///     .locale("fr_FR")
+///     .drafts(true)
+///     .base_url("https://example.com/docs")
+///     .build();
+/// let pages = build.load_pages();
+/// build.build_pages(pages);
+/// ```
+pub struct BuildOptions {
+	/// Path to the Mokk
+	pub root: String,
+	/// Path to the folder the Mokk is output to; by default, the `output` folder of the Mokk
+	pub output: Option<String>,
+	/// Where the files of the Mokk are read from; by default, the Mokk's folder on disk
+	pub source: Option<Arc<dyn SiteSource>>,
+	/// Values of the global file to replace, or to add if the global file lacks them
+	pub global: AHashMap<String, serde_yaml::Value>,
+	/// Liquid filters to add to those provided by Dokkoo
+	pub filters: Vec<Box<dyn ParseFilter>>,
+}
+
+/// The initial state of a `BuildOptions` object, for the Mokk in the current directory
+impl Default for BuildOptions {
+	fn default() -> Self {
+		Self::new(".")
+	}
+}
+
+impl BuildOptions {
+	/// Configures a build of the Mokk at a path
+	///
+	/// # Arguments
+	///
+	/// * `root` - Path to the Mokk
+	pub fn new(root: impl Into<String>) -> Self {
+		Self {
+			root: root.into(),
+			output: None,
+			source: None,
+			global: AHashMap::new(),
+			filters: Vec::new(),
+		}
+	}
+
+	/// Sets the folder the Mokk is output to
+	///
+	/// # Arguments
+	///
+	/// * `output` - Path to the output folder
+	pub fn output(mut self, output: impl Into<String>) -> Self {
+		self.output = Some(output.into());
+		self
+	}
+
+	/// Sets where the files of the Mokk are read from
+	///
+	/// # Arguments
+	///
+	/// * `source` - The source of the Mokk's files
+	pub fn source(mut self, source: Arc<dyn SiteSource>) -> Self {
+		self.source = Some(source);
+		self
+	}
+
+	/// Sets a value of the global file, replacing that in the file if there is one
+	///
+	/// # Arguments
+	///
+	/// * `key` - The key of the value, such as `title`
+	///
+	/// * `value` - The value
+	pub fn global(mut self, key: impl Into<String>, value: impl Into<serde_yaml::Value>) -> Self {
+		self.global.insert(key.into(), value.into());
+		self
+	}
+
+	/// Sets the locale used to format dates
+	///
+	/// # Arguments
+	///
+	/// * `locale` - The locale, such as `en_US`
+	pub fn locale(self, locale: impl Into<String>) -> Self {
+		self.global("locale", locale.into())
+	}
+
+	/// Sets whether pages are minified
+	///
+	/// # Arguments
+	///
+	/// * `minify` - Whether to minify pages
+	pub fn minify(self, minify: bool) -> Self {
+		self.global("minify", minify)
+	}
+
+	/// Sets whether pages marked as drafts are built
+	///
+	/// # Arguments
+	///
+	/// * `drafts` - Whether to build drafts
+	pub fn drafts(self, drafts: bool) -> Self {
+		self.global("drafts", drafts)
+	}
+
+	/// Sets the URL the Mokk is served from, which links between pages begin with
+	///
+	/// # Arguments
+	///
+	/// * `base_url` - The URL, such as `https://example.com/docs`
+	pub fn base_url(self, base_url: impl Into<String>) -> Self {
+		self.global("base_url", base_url.into())
+	}
+
+	/// Adds a Liquid filter, replacing any provided by Dokkoo with the same name
+	///
+	/// # Arguments
+	///
+	/// * `filter` - The filter to add
+	pub fn filter(mut self, filter: impl Into<Box<dyn ParseFilter>>) -> Self {
+		self.filters.push(filter.into());
+		self
+	}
+
+	/// Creates a build with this configuration, reading the Mokk's global file & snippets
+	pub fn build(self) -> Build {
+		Build::from_options(self)
+	}
+}
@@ -0,0 +1,237 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+org.rs - Org-mode
+
+Mokk files written in Org-mode (`format: org`) are rendered with a subset of its syntax: headlines, lists, tables, blocks (`#+BEGIN_SRC`, `#+BEGIN_QUOTE`, `#+BEGIN_EXAMPLE` & others), and inline markup & links.
+Keywords (`#+TITLE:`) & comments are not rendered; a page's metadata belongs in its frontmatter.
+*/
+
+use crate::escape_html;
+use crate::formats::{
+	format_inline, plain_text, render_table, split_table_row, BlockWriter, ContentRenderer,
+	InlineSyntax,
+};
+use crate::highlight::{CodeBlockAttributes, Highlighter};
+use crate::toc::HeadingCollector;
+use std::sync::Arc;
+
+/// The inline syntax of Org-mode
+pub const ORG_INLINE: InlineSyntax = InlineSyntax {
+	delimiters: &[
+		('*', "strong", false),
+		('/', "em", false),
+		('_', "u", false),
+		('+', "del", false),
+		('=', "code", true),
+		('~', "code", true),
+	],
+	link: org_link,
+};
+
+/// Renders an Org-mode link (`[[target][description]]` or `[[target]]`) at the start of some text
+///
+/// # Arguments
+///
+/// * `text` - The text, possibly beginning with a link
+///
+/// * `syntax` - The inline syntax of Org-mode, used to render the link's description
+fn org_link(text: &str, syntax: &InlineSyntax) -> Option<(String, usize)> {
+	let inner = text.strip_prefix("[[")?;
+	let end = inner.find("]]")?;
+	let (target, description) = match inner[..end].split_once("][") {
+		Some((target, description)) => (target, format_inline(description, syntax)),
+		None => (&inner[..end], escape_html(&inner[..end])),
+	};
+	let target = target.strip_prefix("file:").unwrap_or(target);
+	Some((
+		format!("<a href=\"{}\">{description}</a>", escape_html(target)),
+		end + 4,
+	))
+}
+
+/// Returns the list element & text of an Org-mode list item, if a line is one
+///
+/// # Arguments
+///
+/// * `line` - A line of an Org-mode document, without its indentation
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+	if let Some(item) = line.strip_prefix("- ").or(line.strip_prefix("+ ")) {
+		return Some(("ul", item));
+	}
+	let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+	match digits > 0 {
+		true => line[digits..]
+			.strip_prefix(". ")
+			.or(line[digits..].strip_prefix(") "))
+			.map(|item| ("ol", item)),
+		false => None,
+	}
+}
+
+/// Returns a headline's title, without any tags following it (`:tag:other:`)
+///
+/// # Arguments
+///
+/// * `headline` - The text of a headline, following its stars
+fn strip_tags(headline: &str) -> &str {
+	let headline = headline.trim();
+	match headline.rsplit_once(char::is_whitespace) {
+		Some((title, tags))
+			if tags.len() > 1
+				&& tags.starts_with(':')
+				&& tags.ends_with(':')
+				&& !tags.contains(char::is_whitespace) =>
+		{
+			title.trim_end()
+		}
+		_ => headline,
+	}
+}
+
+/// Renders Mokk files written in Org-mode
+pub struct OrgRenderer {
+	/// The syntax highlighter, used for source blocks
+	pub highlighter: Arc<Highlighter>,
+}
+
+impl ContentRenderer for OrgRenderer {
+	fn render(&self, text: &str, heading_collector: &HeadingCollector, _math: bool) -> String {
+		let lines: Vec<&str> = text.lines().collect();
+		let mut writer = BlockWriter::default();
+		let mut caption: Option<String> = None;
+		let mut i = 0;
+		while i < lines.len() {
+			let line = lines[i];
+			let trimmed = line.trim();
+			let upper = trimmed.to_ascii_uppercase();
+			i += 1;
+
+			if trimmed.is_empty() {
+				writer.finish_blocks(&ORG_INLINE);
+				continue;
+			}
+
+			// Blocks, from `#+BEGIN_NAME parameters` to `#+END_NAME`
+			if let Some(block) = upper.strip_prefix("#+BEGIN_") {
+				let name = block.split_whitespace().next().unwrap_or_default();
+				let parameters = trimmed["#+BEGIN_".len() + name.len()..].trim();
+				let end = format!("#+END_{name}");
+				let mut body = String::new();
+				while i < lines.len() && !lines[i].trim().eq_ignore_ascii_case(&end) {
+					body.push_str(lines[i]);
+					body.push('\n');
+					i += 1;
+				}
+				i += 1;
+				let html = match name {
+					"SRC" => {
+						let mut parameters = parameters.split_whitespace();
+						let lang = parameters.next();
+						let mut attributes =
+							CodeBlockAttributes::parse("", self.highlighter.options.line_numbers);
+						attributes.title = caption.take();
+						if parameters.any(|p| p == "-n") {
+							attributes.line_numbers = true;
+						}
+						self.highlighter.render_code_block(&body, lang, &attributes)
+					}
+					"EXAMPLE" | "VERSE" => format!("<pre>{}</pre>\n", escape_html(&body)),
+					"EXPORT" => match parameters.eq_ignore_ascii_case("html") {
+						true => body,
+						false => String::new(),
+					},
+					"COMMENT" => String::new(),
+					"QUOTE" => format!(
+						"<blockquote>\n{}</blockquote>\n",
+						self.render(&body, heading_collector, false)
+					),
+					_ => format!(
+						"<div class=\"{}\">\n{}</div>\n",
+						escape_html(&name.to_lowercase()),
+						self.render(&body, heading_collector, false)
+					),
+				};
+				writer.push_html(&html, &ORG_INLINE);
+				continue;
+			}
+
+			// Keywords, such as `#+TITLE:`, & comments
+			if let Some(keyword) = upper.strip_prefix("#+") {
+				if keyword.starts_with("CAPTION:") {
+					caption = Some(trimmed["#+CAPTION:".len()..].trim().to_owned());
+				}
+				continue;
+			}
+			if trimmed == "#" || trimmed.starts_with("# ") {
+				continue;
+			}
+
+			// Headlines, whose level is their number of stars
+			let stars = line.len() - line.trim_start_matches('*').len();
+			if stars > 0 && line[stars..].starts_with(' ') {
+				let title = strip_tags(&line[stars..]);
+				writer.push_html(
+					&heading_collector.render_heading(
+						stars.min(6) as u8,
+						&plain_text(title, &ORG_INLINE),
+						&format_inline(title, &ORG_INLINE),
+					),
+					&ORG_INLINE,
+				);
+				continue;
+			}
+
+			if trimmed.len() >= 5 && trimmed.chars().all(|c| c == '-') {
+				writer.push_html("<hr />\n", &ORG_INLINE);
+				continue;
+			}
+
+			// Tables, whose header is separated from their body by a rule (`|---+---|`)
+			if trimmed.starts_with('|') {
+				let mut rows: Vec<Vec<String>> = Vec::new();
+				let mut header = false;
+				let mut row = trimmed;
+				loop {
+					match row.starts_with("|-") {
+						true => header |= rows.len() == 1,
+						false => rows.push(split_table_row(row)),
+					}
+					match lines.get(i).map(|l| l.trim()) {
+						Some(next) if next.starts_with('|') => {
+							row = next;
+							i += 1;
+						}
+						_ => break,
+					}
+				}
+				writer.push_html(&render_table(&rows, header, &ORG_INLINE), &ORG_INLINE);
+				continue;
+			}
+
+			if let Some((tag, item)) = list_item(trimmed) {
+				writer.list_item(tag, item, &ORG_INLINE);
+				continue;
+			}
+			if line.starts_with(char::is_whitespace) && writer.continue_list_item(trimmed) {
+				continue;
+			}
+			writer.paragraph_line(trimmed, &ORG_INLINE);
+		}
+		writer.finish(&ORG_INLINE)
+	}
+}
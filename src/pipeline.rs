@@ -0,0 +1,235 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+pipeline.rs - The stages of rendering
+
+Text is rendered by passing it through each of a build's stages in turn: by default, Liquid, then the text's markup format, then LaTeX math, then minification.
+The contents of sanitised pages are not rendered as Liquid.
+Library users may add their own stages, reorder them, or replace the built-in ones.
+*/
+
+use crate::formats::{self, ContentRenderer};
+use crate::math;
+use crate::{render_markdown_with_headings, Build, Page};
+use html_minifier::HTMLMinifier;
+use miette::{miette, IntoDiagnostic, WrapErr};
+
+/// The name of the stage rendering Liquid
+pub const LIQUID_STAGE: &str = "liquid";
+/// The name of the stage rendering the text's markup format, such as Markdown
+pub const CONTENT_STAGE: &str = "content";
+/// The name of the stage rendering LaTeX math in HTML
+pub const MATH_STAGE: &str = "math";
+/// The name of the stage minifying HTML
+pub const MINIFY_STAGE: &str = "minify";
+
+/// What is being rendered, given to each stage of rendering
+pub struct RenderContext<'a> {
+	/// The `.mokkf` file's context as a `Page`
+	pub page: &'a Page,
+	/// The markup format of the text, such as `markdown` or `html`
+	pub format: &'a str,
+	/// Whether or not to render LaTeX Math
+	pub math: bool,
+	/// Whether the text is the contents of the page, rather than its permalink or a layout
+	pub content: bool,
+}
+
+impl RenderContext<'_> {
+	/// Returns whether the text is trusted to be rendered as a template, or by commands; the contents of sanitised pages are not
+	pub fn is_trusted(&self) -> bool {
+		!(self.content && self.page.sanitize)
+	}
+}
+
+/// A stage of rendering, transforming the text rendered by the stage before it
+pub trait RenderStage: Send + Sync {
+	/// The name of the stage, by which it can be found among a build's stages
+	fn name(&self) -> &str;
+
+	/// Returns text transformed by this stage
+	///
+	/// # Arguments
+	///
+	/// * `build` - The build rendering the text
+	///
+	/// * `context` - What is being rendered
+	///
+	/// * `text` - The text rendered by the previous stage
+	fn process(&self, build: &Build, context: &RenderContext, text: String) -> String;
+}
+
+/// Returns the built-in stages of rendering, in their default order
+pub fn default_stages() -> Vec<Box<dyn RenderStage>> {
+	vec![
+		Box::new(LiquidStage),
+		Box::new(ContentStage),
+		Box::new(MathStage),
+		Box::new(MinifyStage),
+	]
+}
+
+/// Renders Liquid
+pub struct LiquidStage;
+
+impl RenderStage for LiquidStage {
+	fn name(&self) -> &str {
+		LIQUID_STAGE
+	}
+
+	fn process(&self, build: &Build, context: &RenderContext, text: String) -> String {
+		let page = context.page;
+		if !build.is_template(context.format) || !context.is_trusted() {
+			return text;
+		}
+		let template = build
+			.liquid_parser
+			.parse(&text)
+			.into_diagnostic()
+			.wrap_err(format!(
+				"Unable to parse text to render ('{text}') for {page:#?}."
+			))
+			.unwrap();
+
+		template
+			.render(&build.get_contexts(page))
+			.into_diagnostic()
+			.wrap_err(format!("Unable to render text ('{text}') for {page:#?}."))
+			.unwrap()
+	}
+}
+
+/// Renders the text's markup format, with the build's content renderer for that format, or with the built-in Markdown renderer
+pub struct ContentStage;
+
+impl RenderStage for ContentStage {
+	fn name(&self) -> &str {
+		CONTENT_STAGE
+	}
+
+	fn process(&self, build: &Build, context: &RenderContext, text: String) -> String {
+		let page = context.page;
+		let registered = build.renderers.contains_key(context.format);
+		match context.format {
+			formats::MARKDOWN if !registered => render_markdown_with_headings(
+				build.render_wiki_links(page, &text),
+				context.math,
+				&build.highlighter,
+				&build.heading_collector,
+				&build.global_context.1.admonitions,
+				&|url| build.resolve_source_link(page, url),
+			),
+			formats::HTML if !registered => text,
+			format => {
+				let renderer = build
+					.renderers
+					.get(format)
+					.ok_or(miette!(
+						"Unable to render '{}', as no renderer is registered for its format ('{}').",
+						page.path(),
+						format
+					))
+					.unwrap();
+				render_content(build, page, renderer.as_ref(), &text, context.math)
+			}
+		}
+	}
+}
+
+/// Returns text rendered by a content renderer, rendering any LaTeX math it contains
+///
+/// # Arguments
+///
+/// * `build` - The build rendering the text
+///
+/// * `page` - The `.mokkf` file's context as a `Page`
+///
+/// * `renderer` - The renderer of the text's format
+///
+/// * `text` - The text to render
+///
+/// * `math` - Whether or not to render LaTeX Math
+fn render_content(
+	build: &Build,
+	page: &Page,
+	renderer: &dyn ContentRenderer,
+	text: &str,
+	math: bool,
+) -> String {
+	match math && renderer.is_template() {
+		true => {
+			let (text, math_regions) = math::extract_math(text);
+			let html = renderer.render(&text, &build.heading_collector, false);
+			build
+				.heading_collector
+				.map_text(|t| math::restore_math_source(t, &math_regions));
+			math::insert_math(&html, &math_regions)
+				.into_diagnostic()
+				.wrap_err(format!(
+					"Unable to render math in document ('{html}') for {page:#?}."
+				))
+				.unwrap()
+		}
+		false => renderer.render(text, &build.heading_collector, math),
+	}
+}
+
+/// Renders LaTeX math in HTML; math in other formats is rendered along with the format
+pub struct MathStage;
+
+impl RenderStage for MathStage {
+	fn name(&self) -> &str {
+		MATH_STAGE
+	}
+
+	fn process(&self, _build: &Build, context: &RenderContext, text: String) -> String {
+		match context.math && context.format == formats::HTML {
+			true => math::render_math(&text)
+				.into_diagnostic()
+				.wrap_err(format!(
+					"Unable to render math in document ('{text}') for {:#?}.",
+					context.page
+				))
+				.unwrap(),
+			false => text,
+		}
+	}
+}
+
+/// Minifies HTML, if the page is intended to be minified
+pub struct MinifyStage;
+
+impl RenderStage for MinifyStage {
+	fn name(&self) -> &str {
+		MINIFY_STAGE
+	}
+
+	fn process(&self, _build: &Build, context: &RenderContext, text: String) -> String {
+		match context.page.minify {
+			true => {
+				let mut html_minifier = HTMLMinifier::new();
+				html_minifier
+					.digest(&text)
+					.into_diagnostic()
+					.wrap_err(format!("Unable to minify HTML for {:#?}.", context.page))
+					.unwrap();
+				String::from_utf8_lossy(html_minifier.get_html()).to_string()
+			}
+			false => text,
+		}
+	}
+}
@@ -0,0 +1,542 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+plugins.rs - WebAssembly plugins
+
+WebAssembly modules in a Mokk's `_plugins` folder are loaded when a build is created, and may provide Liquid filters, transform the HTML of pages, and generate extra pages.
+Plugins may be written in any language compiling to WebAssembly. They are interpreted, and can only call the functions Dokkoo gives them; they cannot access the filesystem, the network, or anything else outside of their own memory.
+Each call into a plugin is limited in how many instructions it may run (its fuel), and plugins are limited in how much memory they may use.
+
+The host interface:
+- Plugins export their memory as `memory`, and a function `dokkoo_alloc(length: i32) -> i32`, returning where Dokkoo may write that many bytes of input.
+- Every other function exported by Dokkoo's interface takes the location & length of its input, `(pointer: i32, length: i32)`, and returns the location & length of its output packed into an `i64`, as `pointer << 32 | length`.
+  If the plugin exports `dokkoo_free(pointer: i32, length: i32)`, Dokkoo calls it once it has read the output; inputs belong to the plugin once written.
+- `dokkoo_filter_<name>` provides the Liquid filter `<name>`, given `{"input": …, "arguments": […]}` as JSON and returning its result as JSON.
+- `dokkoo_transform_html` transforms the HTML of each page once it is rendered, given `{"page": …, "html": "…"}` as JSON and returning the HTML as text.
+- `dokkoo_generate_pages` generates extra pages once the Mokk's pages are loaded, given `{"global": …, "pages": […]}` as JSON and returning `[{"path": "…", "text": "…"}]` as JSON, where each text is that of a Mokk file.
+  Generated pages, & their permalinks, must lie within the Mokk & its output folder respectively.
+- Plugins may import `dokkoo.log(pointer: i32, length: i32)`, writing a message to the console.
+*/
+
+use crate::files::{contained_path, SiteSource};
+use crate::Page;
+use ahash::AHashMap;
+use liquid_core::parser::{FilterArguments, ParameterReflection};
+use liquid_core::{
+	Error, Expression, Filter, FilterReflection, ParseFilter, Result, Runtime, Value, ValueView,
+};
+use miette::{miette, IntoDiagnostic, Severity, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use wasmi::{
+	Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store, StoreLimits,
+	StoreLimitsBuilder,
+};
+
+/// The folder of a Mokk containing its plugins
+pub const PLUGINS_FOLDER: &str = "_plugins";
+/// The function exported by plugins to allocate memory for their input
+pub const ALLOC_EXPORT: &str = "dokkoo_alloc";
+/// The function exported by plugins to free the memory of their output
+pub const FREE_EXPORT: &str = "dokkoo_free";
+/// The prefix of the functions exported by plugins to provide Liquid filters
+pub const FILTER_PREFIX: &str = "dokkoo_filter_";
+/// The function exported by plugins to transform the HTML of pages
+pub const TRANSFORM_HTML_EXPORT: &str = "dokkoo_transform_html";
+/// The function exported by plugins to generate extra pages
+pub const GENERATE_PAGES_EXPORT: &str = "dokkoo_generate_pages";
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+/// Configuration of plugins, from the `plugins` key of the global file
+pub struct PluginOptions {
+	/// The most fuel a plugin may use in one call, roughly the number of instructions it may run
+	pub fuel: u64,
+	/// The most memory a plugin may use, in bytes
+	pub max_memory: usize,
+}
+
+/// The initial state of a `PluginOptions` object
+impl Default for PluginOptions {
+	fn default() -> Self {
+		Self {
+			fuel: 100_000_000,
+			max_memory: 64 * 1024 * 1024,
+		}
+	}
+}
+
+/// A page generated by a plugin
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GeneratedPage {
+	/// The path of the page, as if it were a Mokk file
+	pub path: String,
+	/// The text of the page, including its frontmatter
+	pub text: String,
+}
+
+/// The data of a plugin's store
+struct PluginState {
+	/// The path to the plugin
+	path: String,
+	/// The limits on the plugin's memory
+	limits: StoreLimits,
+}
+
+/// A WebAssembly plugin
+pub struct Plugin {
+	/// The path to the plugin
+	pub path: String,
+	/// The names of the functions the plugin exports
+	pub exports: Vec<String>,
+	/// The fuel the plugin is given for each call
+	fuel: u64,
+	/// The plugin's store, holding its state between calls
+	store: Mutex<Store<PluginState>>,
+	/// The instance of the plugin
+	instance: Instance,
+	/// The memory of the plugin
+	memory: Memory,
+}
+
+impl fmt::Debug for Plugin {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Plugin")
+			.field("path", &self.path)
+			.field("exports", &self.exports)
+			.finish()
+	}
+}
+
+/// Writes a message from a plugin to the console
+///
+/// # Arguments
+///
+/// * `caller` - The plugin calling the function
+///
+/// * `pointer` - Where the message is in the plugin's memory
+///
+/// * `length` - The length of the message, in bytes
+fn log(caller: Caller<'_, PluginState>, pointer: i32, length: i32) {
+	let (pointer, length) = (pointer as u32 as usize, length as u32 as usize);
+	// Messages are only copied once they are known to be within the plugin's memory
+	let message = caller
+		.get_export("memory")
+		.and_then(Extern::into_memory)
+		.and_then(|memory| {
+			memory
+				.data(&caller)
+				.get(pointer..pointer + length)
+				.map(<[u8]>::to_vec)
+		});
+	if let Some(message) = message {
+		eprintln!(
+			"{:?}",
+			miette!(
+				severity = Severity::Advice,
+				"{}: {}",
+				caller.data().path,
+				String::from_utf8_lossy(&message)
+			)
+		);
+	}
+}
+
+impl Plugin {
+	/// Loads a plugin from a WebAssembly module
+	///
+	/// # Arguments
+	///
+	/// * `path` - The path to the plugin
+	///
+	/// * `wasm` - The WebAssembly module, in its binary format
+	///
+	/// * `options` - The configuration of plugins
+	pub fn new(path: String, wasm: &[u8], options: &PluginOptions) -> miette::Result<Self> {
+		let mut config = Config::default();
+		config.consume_fuel(true);
+		let engine = Engine::new(&config);
+		let module = Module::new(&engine, wasm).into_diagnostic()?;
+		let mut store = Store::new(
+			&engine,
+			PluginState {
+				path: path.clone(),
+				limits: StoreLimitsBuilder::new()
+					.memory_size(options.max_memory)
+					.build(),
+			},
+		);
+		store.limiter(|state| &mut state.limits);
+		store.set_fuel(options.fuel).map_err(|e| miette!("{e}"))?;
+
+		let mut linker = Linker::new(&engine);
+		linker.func_wrap("dokkoo", "log", log).into_diagnostic()?;
+		let instance = linker
+			.instantiate(&mut store, &module)
+			.and_then(|instance| instance.start(&mut store))
+			.into_diagnostic()?;
+		let memory = instance.get_memory(&store, "memory").ok_or(miette!(
+			"The plugin does not export its memory as `memory`."
+		))?;
+		instance
+			.get_typed_func::<i32, i32>(&store, ALLOC_EXPORT)
+			.into_diagnostic()
+			.wrap_err(format!(
+				"The plugin does not export `{ALLOC_EXPORT}(length: i32) -> i32`."
+			))?;
+
+		Ok(Self {
+			exports: module.exports().map(|e| e.name().to_owned()).collect(),
+			path,
+			fuel: options.fuel,
+			store: Mutex::new(store),
+			instance,
+			memory,
+		})
+	}
+
+	/// Returns whether the plugin exports a function
+	///
+	/// # Arguments
+	///
+	/// * `name` - The name of the function
+	pub fn exports(&self, name: &str) -> bool {
+		self.exports.iter().any(|export| export == name)
+	}
+
+	/// Calls a function of the plugin, returning its output
+	///
+	/// # Arguments
+	///
+	/// * `name` - The name of the function
+	///
+	/// * `input` - The input given to the function
+	pub fn call(&self, name: &str, input: &[u8]) -> miette::Result<Vec<u8>> {
+		let mut store = self.store.lock().unwrap();
+		store.set_fuel(self.fuel).map_err(|e| miette!("{e}"))?;
+		let alloc = self
+			.instance
+			.get_typed_func::<i32, i32>(&*store, ALLOC_EXPORT)
+			.into_diagnostic()?;
+		let function = self
+			.instance
+			.get_typed_func::<(i32, i32), i64>(&*store, name)
+			.into_diagnostic()
+			.wrap_err(format!(
+				"The plugin does not export `{name}(pointer: i32, length: i32) -> i64`."
+			))?;
+
+		let length = i32::try_from(input.len()).into_diagnostic()?;
+		let pointer = alloc.call(&mut *store, length).into_diagnostic()?;
+		self.memory
+			.write(&mut *store, pointer as u32 as usize, input)
+			.map_err(|e| miette!("{e}"))?;
+		let output = function
+			.call(&mut *store, (pointer, length))
+			.into_diagnostic()?;
+
+		let (pointer, length) = ((output >> 32) as u32, output as u32);
+		// Outputs are only copied once they are known to be within the plugin's memory, so plugins cannot have Dokkoo allocate more memory than they have
+		let buffer = self
+			.memory
+			.data(&*store)
+			.get(pointer as usize..pointer as usize + length as usize)
+			.ok_or(miette!(
+				"The plugin returned an output outside of its memory ({length} bytes at {pointer})."
+			))?
+			.to_vec();
+		if let Ok(free) = self
+			.instance
+			.get_typed_func::<(i32, i32), ()>(&*store, FREE_EXPORT)
+		{
+			free.call(&mut *store, (pointer as i32, length as i32))
+				.into_diagnostic()?;
+		}
+		Ok(buffer)
+	}
+
+	/// Calls a function of the plugin with JSON, returning its output
+	///
+	/// # Arguments
+	///
+	/// * `name` - The name of the function
+	///
+	/// * `input` - The input given to the function, as JSON
+	pub fn call_json(&self, name: &str, input: &serde_json::Value) -> miette::Result<Vec<u8>> {
+		self.call(name, &serde_json::to_vec(input).into_diagnostic()?)
+	}
+}
+
+/// The plugins of a Mokk
+#[derive(Clone, Debug, Default)]
+pub struct Plugins {
+	/// The plugins, in the order of their paths
+	pub plugins: Vec<Arc<Plugin>>,
+}
+
+impl Plugins {
+	/// Loads the plugins of a Mokk
+	///
+	/// # Arguments
+	///
+	/// * `source` - Where the files of the Mokk are read from
+	///
+	/// * `global` - The global context, which may configure plugins under its `plugins` key
+	pub fn load(source: &dyn SiteSource, global: &AHashMap<String, serde_yaml::Value>) -> Self {
+		let paths = source.find(&format!("./{PLUGINS_FOLDER}/**/*.wasm"));
+		if paths.is_empty() {
+			return Self::default();
+		}
+		let options: PluginOptions = match global.get("plugins") {
+			Some(p) => serde_yaml::from_value(p.clone())
+				.into_diagnostic()
+				.wrap_err(format!(
+					"Unable to read `plugins` value ({:?}) from global file.",
+					p
+				))
+				.unwrap(),
+			None => PluginOptions::default(),
+		};
+
+		let mut plugins = Self::default();
+		for path in paths {
+			if source.is_dir(&path) {
+				continue;
+			}
+			let wasm = source
+				.read(&path)
+				.into_diagnostic()
+				.wrap_err(format!(
+					"Unable to read the plugin at '{}'.",
+					path.display()
+				))
+				.unwrap();
+			let path = path.display().to_string();
+			let plugin = Plugin::new(path.clone(), &wasm, &options)
+				.wrap_err(format!("Unable to load the plugin at '{path}'."))
+				.unwrap();
+			plugins.plugins.push(Arc::new(plugin));
+		}
+		plugins
+	}
+
+	/// Returns the plugins exporting a function
+	///
+	/// # Arguments
+	///
+	/// * `name` - The name of the function
+	fn exporting<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Arc<Plugin>> {
+		self.plugins
+			.iter()
+			.filter(move |plugin| plugin.exports(name))
+	}
+
+	/// Returns the Liquid filters provided by the plugins
+	pub fn parse_filters(&self) -> Vec<Box<dyn ParseFilter>> {
+		let mut filters: Vec<Box<dyn ParseFilter>> = Vec::new();
+		for plugin in &self.plugins {
+			for export in &plugin.exports {
+				if let Some(name) = export.strip_prefix(FILTER_PREFIX) {
+					filters.push(Box::new(PluginFilter {
+						name: name.to_owned(),
+						export: export.clone(),
+						plugin: plugin.clone(),
+					}));
+				}
+			}
+		}
+		filters
+	}
+
+	/// Returns the HTML of a page once transformed by the plugins
+	///
+	/// # Arguments
+	///
+	/// * `page` - The `.mokkf` file's context as a `Page`
+	///
+	/// * `html` - The rendered page
+	pub fn transform_html(&self, page: &Page, mut html: String) -> String {
+		for plugin in self.exporting(TRANSFORM_HTML_EXPORT) {
+			let output = plugin
+				.call_json(
+					TRANSFORM_HTML_EXPORT,
+					&serde_json::json!({ "page": page, "html": html }),
+				)
+				.wrap_err(format!(
+					"Unable to transform the HTML of '{}' with the plugin at '{}'.",
+					page.path(),
+					plugin.path
+				))
+				.unwrap();
+			html = String::from_utf8(output)
+				.into_diagnostic()
+				.wrap_err(format!(
+					"The plugin at '{}' returned HTML for '{}' that is not UTF-8.",
+					plugin.path,
+					page.path()
+				))
+				.unwrap();
+		}
+		html
+	}
+
+	/// Returns the pages generated by the plugins
+	///
+	/// # Arguments
+	///
+	/// * `global` - The global context
+	///
+	/// * `pages` - The pages of the Mokk
+	pub fn generate_pages(
+		&self,
+		global: &AHashMap<String, serde_yaml::Value>,
+		pages: &[Page],
+	) -> Vec<GeneratedPage> {
+		let mut generated = Vec::new();
+		for plugin in self.exporting(GENERATE_PAGES_EXPORT) {
+			let output = plugin
+				.call_json(
+					GENERATE_PAGES_EXPORT,
+					&serde_json::json!({ "global": global, "pages": pages }),
+				)
+				.wrap_err(format!(
+					"Unable to generate pages with the plugin at '{}'.",
+					plugin.path
+				))
+				.unwrap();
+			let pages: Vec<GeneratedPage> = serde_json::from_slice(&output)
+				.into_diagnostic()
+				.wrap_err(format!(
+					"Unable to read the pages generated by the plugin at '{}'.",
+					plugin.path
+				))
+				.unwrap();
+			for mut page in pages {
+				// Pages are only generated within the Mokk
+				page.path = contained_path(&page.path)
+					.ok_or(miette!(
+						"The plugin at '{}' generated a page outside of the Mokk ('{}').",
+						plugin.path,
+						page.path
+					))
+					.unwrap()
+					.display()
+					.to_string();
+				generated.push(page);
+			}
+		}
+		generated
+	}
+}
+
+/// Returns a Liquid value as JSON
+///
+/// # Arguments
+///
+/// * `value` - The Liquid value
+fn liquid_to_json(value: &dyn ValueView) -> Result<serde_json::Value> {
+	serde_json::to_value(value.to_value()).map_err(|e| Error::with_msg(e.to_string()))
+}
+
+#[derive(Clone, Debug)]
+/// A Liquid filter provided by a plugin, given the input & any positional arguments, such as `{{ title | shout: "!" }}`
+pub struct PluginFilter {
+	/// The name of the filter
+	name: String,
+	/// The function of the plugin implementing the filter
+	export: String,
+	/// The plugin providing the filter
+	plugin: Arc<Plugin>,
+}
+
+impl FilterReflection for PluginFilter {
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn description(&self) -> &str {
+		"A filter provided by a plugin."
+	}
+
+	fn positional_parameters(&self) -> &'static [ParameterReflection] {
+		&[]
+	}
+
+	fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+		&[]
+	}
+}
+
+impl ParseFilter for PluginFilter {
+	fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+		if arguments.keyword.next().is_some() {
+			return Err(Error::with_msg(format!(
+				"The filter '{}' provided by the plugin at '{}' does not accept keyword arguments.",
+				self.name, self.plugin.path
+			)));
+		}
+		Ok(Box::new(PluginFilterCall {
+			filter: self.clone(),
+			arguments: arguments.positional.collect(),
+		}))
+	}
+
+	fn reflection(&self) -> &dyn FilterReflection {
+		self
+	}
+}
+
+#[derive(Debug)]
+/// A parsed use of a filter provided by a plugin
+struct PluginFilterCall {
+	filter: PluginFilter,
+	arguments: Vec<Expression>,
+}
+
+impl fmt::Display for PluginFilterCall {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.filter.name)
+	}
+}
+
+impl Filter for PluginFilterCall {
+	fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+		let mut arguments = Vec::new();
+		for argument in &self.arguments {
+			arguments.push(liquid_to_json(argument.evaluate(runtime)?.as_view())?);
+		}
+		let input = serde_json::json!({ "input": liquid_to_json(input)?, "arguments": arguments });
+		let output = self
+			.filter
+			.plugin
+			.call_json(&self.filter.export, &input)
+			.map_err(|e| {
+				Error::with_msg(format!(
+					"Unable to run the filter '{}' provided by the plugin at '{}': {e}",
+					self.filter.name, self.filter.plugin.path
+				))
+			})?;
+		let output: serde_json::Value = serde_json::from_slice(&output).map_err(|e| {
+			Error::with_msg(format!(
+				"Unable to read the result of the filter '{}' provided by the plugin at '{}': {e}",
+				self.filter.name, self.filter.plugin.path
+			))
+		})?;
+		liquid_core::to_value(&output)
+	}
+}
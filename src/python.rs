@@ -0,0 +1,320 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+python.rs - The Python API of Dokkoo
+
+With the `python` feature, Dokkoo is a Python extension module (`import dokkoo`), built with maturin.
+Pages are given to Python as dictionaries, and errors raised while building are raised in Python as `dokkoo.DokkooError`, with the message of the diagnostic.
+*/
+
+use crate::highlight::Highlighter;
+use crate::options::BuildOptions;
+use crate::report::BuildReport;
+use crate::{formats, Build, Page};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use pyo3::IntoPyObjectExt;
+use std::sync::OnceLock;
+
+create_exception!(
+	dokkoo,
+	DokkooError,
+	PyException,
+	"An error raised by Dokkoo while building."
+);
+
+/// The syntax highlighter used when rendering Markdown outside of a build
+static HIGHLIGHTER: OnceLock<Highlighter> = OnceLock::new();
+
+/// Runs a function, raising a `DokkooError` if it panics
+///
+/// # Arguments
+///
+/// * `function` - The function to run
+fn catch_error<T>(function: impl FnOnce() -> T) -> PyResult<T> {
+	crate::catch_error(function).map_err(DokkooError::new_err)
+}
+
+/// Returns a JSON value as a Python object
+///
+/// # Arguments
+///
+/// * `py` - A handle to the Python interpreter
+///
+/// * `value` - The value to convert
+fn json_to_python<'py>(py: Python<'py>, value: &serde_json::Value) -> PyResult<Bound<'py, PyAny>> {
+	match value {
+		serde_json::Value::Null => Ok(py.None().into_bound(py)),
+		serde_json::Value::Bool(b) => b.into_bound_py_any(py),
+		serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+			(Some(i), _) => i.into_bound_py_any(py),
+			(None, Some(u)) => u.into_bound_py_any(py),
+			(None, None) => n.as_f64().unwrap_or_default().into_bound_py_any(py),
+		},
+		serde_json::Value::String(s) => s.into_bound_py_any(py),
+		serde_json::Value::Array(values) => {
+			let list = PyList::empty(py);
+			for value in values {
+				list.append(json_to_python(py, value)?)?;
+			}
+			list.into_bound_py_any(py)
+		}
+		serde_json::Value::Object(map) => {
+			let dict = PyDict::new(py);
+			for (key, value) in map {
+				dict.set_item(key, json_to_python(py, value)?)?;
+			}
+			dict.into_bound_py_any(py)
+		}
+	}
+}
+
+/// Returns a Python object as a YAML value, as if given in the global file
+///
+/// # Arguments
+///
+/// * `object` - The object to convert, made of `None`, booleans, numbers, strings, lists, tuples & dictionaries
+fn python_to_yaml(object: &Bound<'_, PyAny>) -> PyResult<serde_yaml::Value> {
+	if object.is_none() {
+		Ok(serde_yaml::Value::Null)
+	} else if object.is_instance_of::<PyBool>() {
+		Ok(serde_yaml::Value::Bool(object.extract()?))
+	} else if object.is_instance_of::<PyInt>() {
+		Ok(serde_yaml::Value::Number(object.extract::<i64>()?.into()))
+	} else if object.is_instance_of::<PyFloat>() {
+		Ok(serde_yaml::Value::Number(object.extract::<f64>()?.into()))
+	} else if object.is_instance_of::<PyString>() {
+		Ok(serde_yaml::Value::String(object.extract()?))
+	} else if let Ok(dict) = object.cast::<PyDict>() {
+		let mut mapping = serde_yaml::Mapping::new();
+		for (key, value) in dict.iter() {
+			mapping.insert(python_to_yaml(&key)?, python_to_yaml(&value)?);
+		}
+		Ok(serde_yaml::Value::Mapping(mapping))
+	} else if object.is_instance_of::<PyList>() || object.is_instance_of::<PyTuple>() {
+		Ok(serde_yaml::Value::Sequence(
+			object
+				.try_iter()?
+				.map(|item| python_to_yaml(&item?))
+				.collect::<PyResult<Vec<serde_yaml::Value>>>()?,
+		))
+	} else {
+		Err(DokkooError::new_err(format!(
+			"Unable to represent {object} as a value of the global file."
+		)))
+	}
+}
+
+/// Returns a page as a Python dictionary
+///
+/// # Arguments
+///
+/// * `py` - A handle to the Python interpreter
+///
+/// * `page` - The `.mokkf` file's context as a `Page`
+fn page_to_python<'py>(py: Python<'py>, page: &Page) -> PyResult<Bound<'py, PyAny>> {
+	let page = serde_json::to_value(page).map_err(|e| {
+		DokkooError::new_err(format!("Unable to represent page as a dictionary: {e}"))
+	})?;
+	json_to_python(py, &page)
+}
+
+/// Returns the report of a build as a Python dictionary, raising a `DokkooError` if any page could not be compiled
+///
+/// # Arguments
+///
+/// * `py` - A handle to the Python interpreter
+///
+/// * `report` - The report of the build
+fn report_to_python<'py>(py: Python<'py>, report: &BuildReport) -> PyResult<Bound<'py, PyAny>> {
+	if let Some(error) = report.errors.first() {
+		return Err(DokkooError::new_err(format!(
+			"Error in '{}': {}",
+			error.path, error.message
+		)));
+	}
+	let report = serde_json::to_value(report).map_err(|e| {
+		DokkooError::new_err(format!("Unable to represent report as a dictionary: {e}"))
+	})?;
+	json_to_python(py, &report)
+}
+
+/// A build of a Mokk
+#[pyclass(name = "Build", module = "dokkoo", unsendable)]
+pub struct PyBuild {
+	/// The build
+	build: Build,
+}
+
+#[pymethods]
+impl PyBuild {
+	/// Creates a build of the Mokk at a path, reading its global file & snippets; values given here take precedence over those of the global file
+	#[new]
+	#[pyo3(signature = (root = ".".to_owned(), output = None, locale = None, minify = None, drafts = None, base_url = None, overrides = None))]
+	#[allow(clippy::too_many_arguments)]
+	fn new(
+		root: String,
+		output: Option<String>,
+		locale: Option<String>,
+		minify: Option<bool>,
+		drafts: Option<bool>,
+		base_url: Option<String>,
+		overrides: Option<&Bound<'_, PyDict>>,
+	) -> PyResult<Self> {
+		let mut options = BuildOptions::new(root);
+		if let Some(output) = output {
+			options = options.output(output);
+		}
+		if let Some(locale) = locale {
+			options = options.locale(locale);
+		}
+		if let Some(minify) = minify {
+			options = options.minify(minify);
+		}
+		if let Some(drafts) = drafts {
+			options = options.drafts(drafts);
+		}
+		if let Some(base_url) = base_url {
+			options = options.base_url(base_url);
+		}
+		if let Some(overrides) = overrides {
+			for (key, value) in overrides.iter() {
+				options = options.global(key.extract::<String>()?, python_to_yaml(&value)?);
+			}
+		}
+		Ok(Self {
+			build: catch_error(|| options.build())?,
+		})
+	}
+
+	/// Path to the Mokk
+	#[getter]
+	fn root(&self) -> String {
+		self.build.root.clone()
+	}
+
+	/// Path to the folder the Mokk is output to
+	#[getter]
+	fn output(&self) -> String {
+		self.build.output.clone()
+	}
+
+	/// The global context, as a dictionary
+	#[getter]
+	fn global_context<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+		let global = serde_json::to_value(&self.build.global_context.0).map_err(|e| {
+			DokkooError::new_err(format!(
+				"Unable to represent global context as a dictionary: {e}"
+			))
+		})?;
+		json_to_python(py, &global)
+	}
+
+	/// Loads & indexes the pages of the Mokk, returning them as dictionaries
+	fn load_pages<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+		let pages = catch_error(|| self.build.load_pages())?;
+		let list = PyList::empty(py);
+		for page in &pages {
+			list.append(page_to_python(py, page)?)?;
+		}
+		Ok(list)
+	}
+
+	/// Returns the page at a path as a dictionary
+	fn page<'py>(&self, py: Python<'py>, path: String) -> PyResult<Bound<'py, PyAny>> {
+		let page = catch_error(|| self.build.get_page_object(path))?;
+		page_to_python(py, &page)
+	}
+
+	/// Compiles the page at a path, returning its HTML
+	fn compile(&mut self, path: String) -> PyResult<String> {
+		catch_error(|| {
+			let page = self.build.get_page_object(path);
+			self.build.compile(page)
+		})
+	}
+
+	/// Renders text as the contents of a page, returning the HTML
+	#[pyo3(signature = (text, format = formats::MARKDOWN.to_owned()))]
+	fn render(&mut self, text: &str, format: String) -> PyResult<String> {
+		catch_error(|| {
+			let format = format.to_lowercase();
+			let page = Page {
+				markdown: format == formats::MARKDOWN,
+				format,
+				math: true,
+				minify: self.build.global_context.1.minify,
+				..Default::default()
+			};
+			self.build.heading_collector.clear();
+			self.build.render_content(&page, text)
+		})
+	}
+
+	/// Builds the Mokk into its output folder, returning a report of the build as a dictionary
+	fn build<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+		let report = catch_error(|| {
+			let pages = self.build.load_pages();
+			self.build.build_pages(pages)
+		})?;
+		report_to_python(py, &report)
+	}
+}
+
+/// Renders Markdown as HTML
+#[pyfunction]
+#[pyo3(signature = (text, math = true))]
+fn render_markdown(text: String, math: bool) -> PyResult<String> {
+	catch_error(|| {
+		crate::render_markdown(text, math, HIGHLIGHTER.get_or_init(Highlighter::default))
+	})
+}
+
+/// Returns a Mokk file's frontmatter & contents, in that order
+#[pyfunction]
+fn split_frontmatter(text: String) -> (String, String) {
+	crate::split_frontmatter(text)
+}
+
+/// Builds the Mokk at a path into its output folder, returning a report of the build as a dictionary
+#[pyfunction]
+#[pyo3(signature = (root, output = None))]
+fn build_site(py: Python<'_>, root: String, output: Option<String>) -> PyResult<Bound<'_, PyAny>> {
+	let report = catch_error(|| {
+		let mut options = BuildOptions::new(root);
+		if let Some(output) = output {
+			options = options.output(output);
+		}
+		let mut build = options.build();
+		let pages = build.load_pages();
+		build.build_pages(pages)
+	})?;
+	report_to_python(py, &report)
+}
+
+/// Dokkoo, a Mokk (Macro Output Key Kit) implementation written in Rust
+#[pymodule]
+fn dokkoo(module: &Bound<'_, PyModule>) -> PyResult<()> {
+	module.add("DokkooError", module.py().get_type::<DokkooError>())?;
+	module.add_class::<PyBuild>()?;
+	module.add_function(wrap_pyfunction!(render_markdown, module)?)?;
+	module.add_function(wrap_pyfunction!(split_frontmatter, module)?)?;
+	module.add_function(wrap_pyfunction!(build_site, module)?)?;
+	Ok(())
+}
@@ -0,0 +1,171 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+report.rs - Reports of builds
+
+Building a Mokk returns a report of what was built: the pages compiled & skipped, the files written to the output folder & their sizes, how long each page took to render, and the warnings & errors raised.
+Reports are printed as a table, or serialised as JSON (such as with `dokkoo build --report json`) so that builds can be tracked over time.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(PartialEq, Clone, Default, Debug, Serialize, Deserialize)]
+/// A page compiled while building a Mokk
+pub struct PageReport {
+	/// Path to the page's Mokk file
+	pub path: String,
+	/// The page's URL, relative to the output folder
+	pub url: String,
+	/// The size of the compiled page, in bytes
+	pub bytes: usize,
+	/// How long the page took to compile, in seconds
+	pub seconds: f64,
+}
+
+#[derive(Eq, PartialEq, Clone, Default, Debug, Serialize, Deserialize)]
+/// A file written to the output folder other than a page, such as a stylesheet or a file returned by a script
+pub struct FileReport {
+	/// Path to the file, relative to the output folder
+	pub path: String,
+	/// The size of the file, in bytes
+	pub bytes: usize,
+}
+
+#[derive(Eq, PartialEq, Clone, Default, Debug, Serialize, Deserialize)]
+/// An error raised while loading or compiling a page, which is then not written
+pub struct ErrorReport {
+	/// Path to the page's Mokk file, or to the plugins folder if plugins could not generate their pages
+	pub path: String,
+	/// The message of the error
+	pub message: String,
+}
+
+#[derive(PartialEq, Clone, Default, Debug, Serialize, Deserialize)]
+/// What was built while building a Mokk
+pub struct BuildReport {
+	/// Path to the folder the Mokk was output to
+	pub output: String,
+	/// The pages compiled, in the order they were compiled
+	pub pages: Vec<PageReport>,
+	/// Paths to the Mokk files of the pages that were not built, such as unpublished drafts
+	pub skipped: Vec<String>,
+	/// The files written to the output folder other than pages
+	pub files: Vec<FileReport>,
+	/// The messages of the warnings raised while building
+	pub warnings: Vec<String>,
+	/// The errors raised while loading or compiling pages
+	pub errors: Vec<ErrorReport>,
+	/// How long the build took, in seconds
+	pub seconds: f64,
+}
+
+impl BuildReport {
+	/// Returns the number of bytes written to the output folder
+	pub fn written_bytes(&self) -> usize {
+		self.pages.iter().map(|page| page.bytes).sum::<usize>()
+			+ self.files.iter().map(|file| file.bytes).sum::<usize>()
+	}
+
+	/// Returns the pages compiled, from slowest to fastest
+	pub fn slowest_pages(&self) -> Vec<&PageReport> {
+		let mut pages: Vec<&PageReport> = self.pages.iter().collect();
+		pages.sort_by(|a, b| b.seconds.total_cmp(&a.seconds));
+		pages
+	}
+
+	/// Returns the report as JSON
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap()
+	}
+}
+
+/// Prints the report as a table of the pages compiled & the files written, followed by the pages skipped, the warnings & errors raised, and a summary
+impl fmt::Display for BuildReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let rows: Vec<[String; 3]> = self
+			.pages
+			.iter()
+			.map(|page| {
+				[
+					page.url.clone(),
+					page.bytes.to_string(),
+					format!("{:.2}", page.seconds * 1000.0),
+				]
+			})
+			.chain(
+				self.files
+					.iter()
+					.map(|file| [file.path.clone(), file.bytes.to_string(), String::new()]),
+			)
+			.collect();
+		let header = ["Output", "Bytes", "Time (ms)"];
+		let widths: Vec<usize> = (0..header.len())
+			.map(|i| {
+				rows.iter()
+					.map(|row| row[i].chars().count())
+					.chain([header[i].len()])
+					.max()
+					.unwrap_or_default()
+			})
+			.collect();
+		writeln!(
+			f,
+			"{:<w0$}  {:>w1$}  {:>w2$}",
+			header[0],
+			header[1],
+			header[2],
+			w0 = widths[0],
+			w1 = widths[1],
+			w2 = widths[2]
+		)?;
+		writeln!(f, "{}", "-".repeat(widths.iter().sum::<usize>() + 4))?;
+		for row in &rows {
+			writeln!(
+				f,
+				"{:<w0$}  {:>w1$}  {:>w2$}",
+				row[0],
+				row[1],
+				row[2],
+				w0 = widths[0],
+				w1 = widths[1],
+				w2 = widths[2]
+			)?;
+		}
+
+		for skipped in &self.skipped {
+			writeln!(f, "Skipped: {skipped}")?;
+		}
+		for warning in &self.warnings {
+			writeln!(f, "Warning: {warning}")?;
+		}
+		for error in &self.errors {
+			writeln!(f, "Error in '{}': {}", error.path, error.message)?;
+		}
+		write!(
+			f,
+			"{} pages built, {} skipped, {} files written, {} bytes in total, {} warnings, {} errors, in {:.2} seconds.",
+			self.pages.len(),
+			self.skipped.len(),
+			self.files.len(),
+			self.written_bytes(),
+			self.warnings.len(),
+			self.errors.len(),
+			self.seconds
+		)
+	}
+}
@@ -0,0 +1,179 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+sanitize.rs - Sanitising HTML rendered from untrusted Mokk files
+
+The rendered contents of Mokk files written by untrusted contributors are cleaned with Ammonia, which only keeps allowlisted tags & attributes.
+Besides Ammonia's defaults, the markup Dokkoo itself renders is allowed: MathML, and the inline styles of highlighted code, limited to the CSS properties the highlighter uses.
+Sanitised contents are not rendered as Liquid, nor by commands, and once sanitised, their Liquid delimiters are escaped, so that contents embedded in layouts are not rendered as Liquid either.
+Pages are sanitised if they are in a sanitised collection or folder, whatever their frontmatter says; other pages may opt in with `sanitize: true`.
+Layouts are trusted, and are not sanitised.
+*/
+
+use ahash::AHashMap;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+/// The MathML tags rendered from LaTeX math
+pub const MATHML_TAGS: [&str; 21] = [
+	"math",
+	"mfrac",
+	"mi",
+	"mmultiscripts",
+	"mn",
+	"mo",
+	"mover",
+	"mroot",
+	"mrow",
+	"mspace",
+	"msqrt",
+	"mstyle",
+	"msub",
+	"msubsup",
+	"msup",
+	"mtable",
+	"mtd",
+	"mtext",
+	"mtr",
+	"munder",
+	"munderover",
+];
+
+/// The attributes of the MathML tags rendered from LaTeX math, keyed by their tags
+pub const MATHML_ATTRIBUTES: [(&str, &[&str]); 10] = [
+	("math", &["xmlns", "display"]),
+	("mi", &["mathvariant"]),
+	("mo", &["mathvariant", "stretchy", "form", "accent"]),
+	("mfrac", &["linethickness"]),
+	("mspace", &["width"]),
+	("mstyle", &["displaystyle"]),
+	("mtable", &["columnalign"]),
+	("mover", &["accent"]),
+	("munder", &["accent"]),
+	("munderover", &["accent"]),
+];
+
+/// The tags given IDs by Dokkoo: headings, numbered equations & footnotes
+pub const ID_TAGS: [&str; 9] = ["h1", "h2", "h3", "h4", "h5", "h6", "span", "li", "a"];
+
+/// The tags given classes by Dokkoo, such as highlighted code, admonitions, notebook cells & footnotes
+pub const CLASS_TAGS: [&str; 10] = [
+	"a", "aside", "code", "div", "li", "p", "pre", "section", "span", "sup",
+];
+
+/// The tags styled inline by the syntax highlighter
+pub const STYLED_TAGS: [&str; 2] = ["pre", "span"];
+
+/// The CSS properties of the inline styles of highlighted code
+pub const STYLE_PROPERTIES: [&str; 10] = [
+	"background-color",
+	"color",
+	"display",
+	"font-style",
+	"font-weight",
+	"opacity",
+	"padding-right",
+	"text-decoration",
+	"user-select",
+	"width",
+];
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+/// Configuration of HTML sanitisation, from the `sanitize` key of the global file
+pub struct SanitizeOptions {
+	/// The collections whose pages are sanitised, whatever their `sanitize` frontmatter says
+	pub collections: Vec<String>,
+	/// Glob patterns of the Mokk files sanitised whatever their frontmatter says, relative to the root of the Mokk, such as `contributions/**`
+	pub paths: Vec<String>,
+	/// Tags allowed in addition to Ammonia's defaults & the markup rendered by Dokkoo
+	pub tags: Vec<String>,
+	/// Tags removed from Ammonia's defaults
+	pub remove_tags: Vec<String>,
+	/// Attributes allowed on each tag in addition to Ammonia's defaults; those under `*` are allowed on every tag
+	pub attributes: AHashMap<String, Vec<String>>,
+}
+
+/// The initial state of a `SanitizeOptions` object
+impl Default for SanitizeOptions {
+	fn default() -> Self {
+		Self {
+			collections: Vec::new(),
+			paths: Vec::new(),
+			tags: Vec::new(),
+			remove_tags: Vec::new(),
+			attributes: AHashMap::new(),
+		}
+	}
+}
+
+impl SanitizeOptions {
+	/// Returns whether a page is sanitised whatever its frontmatter says, as its collection or its path is
+	///
+	/// # Arguments
+	///
+	/// * `collection` - The name of the page's collection, if it is in one
+	///
+	/// * `path` - The path to the Mokk file, relative to the root of the Mokk
+	pub fn is_sanitized(&self, collection: Option<&str>, path: &str) -> bool {
+		let path = path.trim_start_matches("./");
+		collection.is_some_and(|collection| self.collections.iter().any(|c| c == collection))
+			|| self
+				.paths
+				.iter()
+				.filter_map(|pattern| Pattern::new(pattern.trim_start_matches("./")).ok())
+				.any(|pattern| pattern.matches(path))
+	}
+
+	/// Returns HTML with everything not allowlisted removed, and its Liquid delimiters escaped
+	///
+	/// # Arguments
+	///
+	/// * `html` - The HTML to sanitise
+	#[cfg(feature = "sanitize")]
+	pub fn sanitize(&self, html: &str) -> String {
+		let mut builder = ammonia::Builder::default();
+		builder
+			.add_tags(MATHML_TAGS)
+			.filter_style_properties(STYLE_PROPERTIES.into_iter().collect());
+		for (tag, attributes) in MATHML_ATTRIBUTES {
+			builder.add_tag_attributes(tag, attributes.iter().copied());
+		}
+		for tag in STYLED_TAGS {
+			builder.add_tag_attributes(tag, ["style"]);
+		}
+		for tag in ID_TAGS {
+			builder.add_tag_attributes(tag, ["id"]);
+		}
+		for tag in CLASS_TAGS {
+			builder.add_tag_attributes(tag, ["class"]);
+		}
+		builder
+			.add_tag_attributes("span", ["aria-hidden"])
+			.add_tag_attributes("a", ["aria-hidden"])
+			.add_tags(self.tags.iter().map(String::as_str))
+			.rm_tags(self.remove_tags.iter().map(String::as_str));
+		for (tag, attributes) in &self.attributes {
+			let attributes = attributes.iter().map(String::as_str);
+			match tag.as_str() {
+				"*" => builder.add_generic_attributes(attributes),
+				tag => builder.add_tag_attributes(tag, attributes),
+			};
+		}
+		crate::escape_liquid(&builder.clean(html).to_string())
+	}
+}
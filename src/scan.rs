@@ -0,0 +1,244 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+scan.rs - Scanning Markdown & HTML outside of code
+
+Helpers for the passes made over a document before it is rendered, such as extracting math, which must leave code untouched.
+*/
+
+/// The HTML elements whose contents are never treated as text to be processed
+const VERBATIM_ELEMENTS: [&str; 5] = ["code", "pre", "script", "style", "math"];
+
+/// Returns text with everything outside of fenced code blocks replaced, leaving the code blocks as-is
+///
+/// # Arguments
+///
+/// * `text` - The text of a Markdown document
+///
+/// * `replace` - Returns the replacement for a run of text between fenced code blocks
+pub fn replace_outside_code_blocks(text: &str, mut replace: impl FnMut(&str) -> String) -> String {
+	let mut output = String::with_capacity(text.len());
+	let mut prose = String::new();
+	let mut fence: Option<(char, usize)> = None;
+
+	for line in text.split_inclusive('\n') {
+		match fence {
+			Some((fence_char, fence_length)) => {
+				output.push_str(line);
+				if let Some((c, length, rest)) = parse_fence(line) {
+					if c == fence_char && length >= fence_length && rest.trim().is_empty() {
+						fence = None;
+					}
+				}
+			}
+			None => match parse_fence(line) {
+				Some((c, length, rest)) if c == '~' || !rest.contains('`') => {
+					output.push_str(&replace(&prose));
+					prose.clear();
+					output.push_str(line);
+					fence = Some((c, length));
+				}
+				_ => prose.push_str(line),
+			},
+		}
+	}
+	output.push_str(&replace(&prose));
+
+	output
+}
+
+/// Returns text with fenced code blocks replaced, leaving the rest of the text as-is
+///
+/// # Arguments
+///
+/// * `text` - The text of a Markdown document
+///
+/// * `replace` - Returns the replacement for a fenced code block, given its info string & code, or `None` to leave it as-is
+pub fn replace_code_blocks(
+	text: &str,
+	mut replace: impl FnMut(&str, &str) -> Option<String>,
+) -> String {
+	let mut output = String::with_capacity(text.len());
+	let mut block = String::new();
+	let mut code = String::new();
+	let mut info = String::new();
+	let mut fence: Option<(char, usize)> = None;
+
+	for line in text.split_inclusive('\n') {
+		match fence {
+			Some((fence_char, fence_length)) => {
+				block.push_str(line);
+				match parse_fence(line) {
+					Some((c, length, rest))
+						if c == fence_char && length >= fence_length && rest.trim().is_empty() =>
+					{
+						output.push_str(&replace(&info, &code).unwrap_or(block.clone()));
+						fence = None;
+					}
+					_ => code.push_str(line),
+				}
+			}
+			None => match parse_fence(line) {
+				Some((c, length, rest)) if c == '~' || !rest.contains('`') => {
+					block = line.to_owned();
+					code.clear();
+					info = rest.trim().to_owned();
+					fence = Some((c, length));
+				}
+				_ => output.push_str(line),
+			},
+		}
+	}
+	// Code blocks left open are closed by the end of the document
+	if fence.is_some() {
+		output.push_str(&replace(&info, &code).unwrap_or(block));
+	}
+	output
+}
+
+/// Returns the character & length of the fence opening or closing a fenced code block, along with the text following it, if a line is one
+///
+/// # Arguments
+///
+/// * `line` - A line of a document
+pub fn parse_fence(line: &str) -> Option<(char, usize, &str)> {
+	let trimmed = line.trim_start_matches(' ');
+	if line.len() - trimmed.len() > 3 {
+		return None;
+	}
+	let c = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+	let length = trimmed.len() - trimmed.trim_start_matches(c).len();
+	match length >= 3 {
+		true => Some((c, length, &trimmed[length..])),
+		false => None,
+	}
+}
+
+/// Returns the position following a code span, or following its opening backticks if they are never closed
+///
+/// # Arguments
+///
+/// * `text` - The text containing the code span
+///
+/// * `start` - The position of the code span's opening backticks
+pub fn skip_code_span(text: &str, start: usize) -> usize {
+	let length = text[start..].len() - text[start..].trim_start_matches('`').len();
+	let mut i = start + length;
+	while let Some(offset) = text[i..].find('`') {
+		let run_start = i + offset;
+		let run_length = text[run_start..].len() - text[run_start..].trim_start_matches('`').len();
+		if run_length == length {
+			return run_start + run_length;
+		}
+		i = run_start + run_length;
+	}
+	start + length
+}
+
+/// Returns the position following an HTML comment or tag, or following the element if its contents are never processed
+///
+/// # Arguments
+///
+/// * `text` - The text containing the HTML
+///
+/// * `start` - The position of the opening `<`
+pub fn skip_html(text: &str, start: usize) -> usize {
+	let rest = &text[start..];
+	if rest.starts_with("<!--") {
+		return rest
+			.find("-->")
+			.map_or(start + 4, |end| start + end + "-->".len());
+	}
+	let name: String = rest[1..]
+		.chars()
+		.take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+		.collect();
+	if name.is_empty() || !rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+		return match rest[1..].starts_with('/') {
+			true => rest.find('>').map_or(start + 1, |end| start + end + 1),
+			false => start + 1,
+		};
+	}
+	let tag_end = match rest.find('>') {
+		Some(end) => start + end + 1,
+		None => return start + 1,
+	};
+	let name = name.to_ascii_lowercase();
+	if !VERBATIM_ELEMENTS.contains(&name.as_str()) {
+		return tag_end;
+	}
+	let closing_tag = format!("</{name}");
+	text[tag_end..]
+		.to_ascii_lowercase()
+		.find(&closing_tag)
+		.map_or(tag_end, |end| tag_end + end + closing_tag.len())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn replaces_only_outside_code_blocks() {
+		let text = "a\n```rust\na\n```\na\n~~~\na\n~~~\n";
+		assert_eq!(
+			replace_outside_code_blocks(text, |prose| prose.replace('a', "b")),
+			"b\n```rust\na\n```\nb\n~~~\na\n~~~\n"
+		);
+	}
+
+	#[test]
+	fn closes_code_blocks_only_with_matching_fences() {
+		let text = "````\n```\n~~~~\na\n````\na\n";
+		assert_eq!(
+			replace_outside_code_blocks(text, |prose| prose.replace('a', "b")),
+			"````\n```\n~~~~\na\n````\nb\n"
+		);
+	}
+
+	#[test]
+	fn leaves_unclosed_code_blocks_alone() {
+		assert_eq!(
+			replace_outside_code_blocks("a\n```\na\n", |prose| prose.replace('a', "b")),
+			"b\n```\na\n"
+		);
+	}
+
+	#[test]
+	fn parses_fences() {
+		assert_eq!(parse_fence("```rust\n"), Some(('`', 3, "rust\n")));
+		assert_eq!(parse_fence("   ~~~~\n"), Some(('~', 4, "\n")));
+		assert_eq!(parse_fence("    ```\n"), None);
+		assert_eq!(parse_fence("``\n"), None);
+		assert_eq!(parse_fence("text\n"), None);
+	}
+
+	#[test]
+	fn backtick_fences_cannot_contain_backticks() {
+		assert_eq!(
+			replace_outside_code_blocks("```a`\na\n", |prose| prose.replace('a', "b")),
+			"```b`\nb\n"
+		);
+	}
+
+	#[test]
+	fn skips_code_spans() {
+		assert_eq!(skip_code_span("`a` b", 0), 3);
+		assert_eq!(skip_code_span("``a ` b`` c", 0), 9);
+		assert_eq!(skip_code_span("x `a", 2), 3);
+	}
+}
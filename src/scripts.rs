@@ -0,0 +1,588 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+scripts.rs - Site-local scripts
+
+Rhai scripts in a Mokk's `_scripts` folder are run when a build is created, and may register Liquid filters & tags, and hooks called during the build:
+```rhai
+register_filter("shout", |text| text.to_upper() + "!");
+register_tag("year", || 2024);
+on("before_render", |page, collections| { page.data.reading_time = page.content.len() / 1000; page });
+on("after_render", |page, html| { html.replace("TODO", "DONE"); html });
+on("after_build", |collections| #{ "pages.txt": collections.keys().len().to_string() });
+```
+Scripts are sandboxed: they cannot import modules, evaluate code they build, or access the filesystem, and each call is limited in how many operations it may perform.
+With `scripts: { read_files: true }` in the global file, scripts may read the files of the Mokk with `read_file(path)`.
+*/
+
+use crate::files::{contained_path, SiteSource};
+use crate::Page;
+use ahash::AHashMap;
+use liquid_core::error::{ResultLiquidExt, ResultLiquidReplaceExt};
+use liquid_core::parser::{FilterArguments, ParameterReflection};
+use liquid_core::{
+	Error, Expression, Filter, FilterReflection, Language, ParseFilter, ParseTag, Renderable,
+	Result, Runtime, TagReflection, TagTokenIter, Value, ValueView,
+};
+use miette::{miette, IntoDiagnostic, WrapErr};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::serde::{from_dynamic, to_dynamic};
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, AST};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// The folder of a Mokk containing its scripts
+pub const SCRIPTS_FOLDER: &str = "_scripts";
+/// The hook called before a page is rendered, given the page & collections; it may return a changed page
+pub const BEFORE_RENDER: &str = "before_render";
+/// The hook called after a page is rendered, given the page & its HTML; it may return changed HTML
+pub const AFTER_RENDER: &str = "after_render";
+/// The hook called after a Mokk is built, given its collections; it may return a map of extra files to write to the output folder
+pub const AFTER_BUILD: &str = "after_build";
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+/// Configuration of scripts, from the `scripts` key of the global file
+pub struct ScriptOptions {
+	/// Whether scripts may read the files of the Mokk
+	pub read_files: bool,
+	/// The most operations a script may perform in one call, stopping scripts which would never finish
+	pub max_operations: u64,
+}
+
+/// The initial state of a `ScriptOptions` object
+impl Default for ScriptOptions {
+	fn default() -> Self {
+		Self {
+			read_files: false,
+			max_operations: 10_000_000,
+		}
+	}
+}
+
+/// What a script registers a function as
+enum Registration {
+	/// A Liquid filter, with its name
+	Filter(String),
+	/// A Liquid tag, with its name
+	Tag(String),
+	/// A hook, with the name of its event
+	Hook(String),
+}
+
+/// The functions registered by the script being run
+type Registrations = Arc<Mutex<Vec<(Registration, FnPtr)>>>;
+
+#[derive(Clone)]
+/// A function defined by a script
+pub struct ScriptFunction {
+	/// The path to the script defining the function
+	pub path: String,
+	/// The function
+	function: FnPtr,
+	/// The script defining the function
+	ast: Arc<AST>,
+	/// The engine running the script
+	engine: Arc<Engine>,
+}
+
+impl fmt::Debug for ScriptFunction {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ScriptFunction")
+			.field("path", &self.path)
+			.field("function", &self.function)
+			.finish()
+	}
+}
+
+impl ScriptFunction {
+	/// Calls the function, returning its result
+	///
+	/// # Arguments
+	///
+	/// * `arguments` - The arguments given to the function
+	pub fn call(
+		&self,
+		arguments: Vec<Dynamic>,
+	) -> std::result::Result<Dynamic, Box<EvalAltResult>> {
+		self.function.call(&self.engine, &self.ast, arguments)
+	}
+}
+
+/// The scripts of a Mokk, with the filters, tags & hooks they register
+#[derive(Clone, Debug, Default)]
+pub struct Scripts {
+	/// The Liquid filters registered, keyed by their names
+	pub filters: Vec<(String, ScriptFunction)>,
+	/// The Liquid tags registered, keyed by their names
+	pub tags: Vec<(String, ScriptFunction)>,
+	/// The hooks registered, keyed by the names of their events
+	pub hooks: Vec<(String, ScriptFunction)>,
+}
+
+impl Scripts {
+	/// Runs the scripts of a Mokk, in the order of their paths, collecting what they register
+	///
+	/// # Arguments
+	///
+	/// * `source` - Where the files of the Mokk are read from
+	///
+	/// * `global` - The global context, which may configure scripts under its `scripts` key
+	pub fn load(source: Arc<dyn SiteSource>, global: &AHashMap<String, serde_yaml::Value>) -> Self {
+		let paths = source.find(&format!("./{SCRIPTS_FOLDER}/**/*.rhai"));
+		if paths.is_empty() {
+			return Self::default();
+		}
+		let options: ScriptOptions = match global.get("scripts") {
+			Some(s) => serde_yaml::from_value(s.clone())
+				.into_diagnostic()
+				.wrap_err(format!(
+					"Unable to read `scripts` value ({:?}) from global file.",
+					s
+				))
+				.unwrap(),
+			None => ScriptOptions::default(),
+		};
+
+		let registrations: Registrations = Arc::new(Mutex::new(Vec::new()));
+		let engine = Arc::new(create_engine(
+			&options,
+			source.clone(),
+			registrations.clone(),
+		));
+		let mut scripts = Self::default();
+		for path in paths {
+			if source.is_dir(&path) {
+				continue;
+			}
+			let path = path.display().to_string();
+			let text = source
+				.read_to_string(Path::new(&path))
+				.into_diagnostic()
+				.wrap_err(format!("Unable to read the script at '{path}'."))
+				.unwrap();
+			let ast = Arc::new(
+				engine
+					.compile(&text)
+					.into_diagnostic()
+					.wrap_err(format!("Unable to parse the script at '{path}'."))
+					.unwrap(),
+			);
+			engine
+				.run_ast(&ast)
+				.into_diagnostic()
+				.wrap_err(format!("Unable to run the script at '{path}'."))
+				.unwrap();
+
+			for (registration, function) in registrations.lock().unwrap().drain(..) {
+				let function = ScriptFunction {
+					path: path.clone(),
+					function,
+					ast: ast.clone(),
+					engine: engine.clone(),
+				};
+				match registration {
+					Registration::Filter(name) => scripts.filters.push((name, function)),
+					Registration::Tag(name) => scripts.tags.push((name, function)),
+					Registration::Hook(event) => scripts.hooks.push((event, function)),
+				}
+			}
+		}
+		scripts
+	}
+
+	/// Returns the Liquid filters registered by the scripts
+	pub fn parse_filters(&self) -> Vec<Box<dyn ParseFilter>> {
+		self.filters
+			.iter()
+			.map(|(name, function)| {
+				Box::new(ScriptFilter {
+					name: name.clone(),
+					function: function.clone(),
+				}) as Box<dyn ParseFilter>
+			})
+			.collect()
+	}
+
+	/// Returns the Liquid tags registered by the scripts
+	pub fn parse_tags(&self) -> Vec<Box<dyn ParseTag>> {
+		self.tags
+			.iter()
+			.map(|(name, function)| {
+				Box::new(ScriptTag {
+					// Liquid requires the names of tags to live as long as the program
+					name: Box::leak(name.clone().into_boxed_str()),
+					function: function.clone(),
+				}) as Box<dyn ParseTag>
+			})
+			.collect()
+	}
+
+	/// Returns the hooks registered for an event
+	///
+	/// # Arguments
+	///
+	/// * `event` - The name of the event
+	fn hooks<'a>(&'a self, event: &'a str) -> impl Iterator<Item = &'a ScriptFunction> {
+		self.hooks
+			.iter()
+			.filter(move |(name, _)| name == event)
+			.map(|(_, function)| function)
+	}
+
+	/// Calls the `before_render` hooks, replacing the page with any they return
+	///
+	/// # Arguments
+	///
+	/// * `page` - The `.mokkf` file's context as a `Page`
+	///
+	/// * `collections` - The pages compiled so far, grouped by their collection name
+	pub fn before_render(&self, page: &mut Page, collections: &AHashMap<String, Vec<Page>>) {
+		for hook in self.hooks(BEFORE_RENDER) {
+			let result = to_dynamic(&*page)
+				.and_then(|p| Ok((p, to_dynamic(collections)?)))
+				.and_then(|(p, c)| hook.call(vec![p, c]))
+				.into_diagnostic()
+				.wrap_err(format!(
+					"Unable to run the `{BEFORE_RENDER}` hook of the script at '{}' for '{}'.",
+					hook.path,
+					page.path()
+				))
+				.unwrap();
+			if !result.is_unit() {
+				*page = from_dynamic(&result)
+					.into_diagnostic()
+					.wrap_err(format!(
+						"Unable to read the page returned by the `{BEFORE_RENDER}` hook of the script at '{}' for '{}'.",
+						hook.path,
+						page.path()
+					))
+					.unwrap();
+			}
+		}
+	}
+
+	/// Calls the `after_render` hooks, returning the HTML of the page as changed by them
+	///
+	/// # Arguments
+	///
+	/// * `page` - The `.mokkf` file's context as a `Page`
+	///
+	/// * `html` - The rendered page
+	pub fn after_render(&self, page: &Page, mut html: String) -> String {
+		for hook in self.hooks(AFTER_RENDER) {
+			let result = to_dynamic(page)
+				.and_then(|p| hook.call(vec![p, html.clone().into()]))
+				.into_diagnostic()
+				.wrap_err(format!(
+					"Unable to run the `{AFTER_RENDER}` hook of the script at '{}' for '{}'.",
+					hook.path,
+					page.path()
+				))
+				.unwrap();
+			if !result.is_unit() {
+				html = result
+					.into_string()
+					.map_err(|t| {
+						miette!(
+							"The `{}` hook of the script at '{}' returned a {} for '{}', rather than a string.",
+							AFTER_RENDER,
+							hook.path,
+							t,
+							page.path()
+						)
+					})
+					.unwrap();
+			}
+		}
+		html
+	}
+
+	/// Calls the `after_build` hooks, returning the extra files they return, keyed by their paths in the output folder
+	///
+	/// # Arguments
+	///
+	/// * `collections` - The pages of the Mokk, grouped by their collection name
+	pub fn after_build(&self, collections: &AHashMap<String, Vec<Page>>) -> Vec<(String, String)> {
+		let mut files = Vec::new();
+		for hook in self.hooks(AFTER_BUILD) {
+			let result = to_dynamic(collections)
+				.and_then(|c| hook.call(vec![c]))
+				.into_diagnostic()
+				.wrap_err(format!(
+					"Unable to run the `{AFTER_BUILD}` hook of the script at '{}'.",
+					hook.path
+				))
+				.unwrap();
+			if result.is_unit() {
+				continue;
+			}
+			let map = result
+				.try_cast::<rhai::Map>()
+				.ok_or(miette!(
+					"The `{}` hook of the script at '{}' returned a value other than a map of files.",
+					AFTER_BUILD,
+					hook.path
+				))
+				.unwrap();
+			for (path, text) in map {
+				// Files are only written within the output folder
+				let path = contained_path(path.as_str())
+					.ok_or(miette!(
+						"The `{}` hook of the script at '{}' returned a file outside of the output folder ('{}').",
+						AFTER_BUILD,
+						hook.path,
+						path
+					))
+					.unwrap();
+				files.push((path.display().to_string(), text.to_string()));
+			}
+		}
+		files
+	}
+}
+
+/// Creates the sandboxed engine running a Mokk's scripts
+///
+/// # Arguments
+///
+/// * `options` - The configuration of scripts
+///
+/// * `source` - Where the files of the Mokk are read from
+///
+/// * `registrations` - Where the functions registered by scripts are recorded
+fn create_engine(
+	options: &ScriptOptions,
+	source: Arc<dyn SiteSource>,
+	registrations: Registrations,
+) -> Engine {
+	let mut engine = Engine::new();
+	// Scripts may not import modules from disk, nor evaluate code they build
+	engine.set_module_resolver(DummyModuleResolver::new());
+	engine.disable_symbol("eval");
+	engine.set_max_operations(options.max_operations);
+
+	let filters = registrations.clone();
+	engine.register_fn("register_filter", move |name: &str, function: FnPtr| {
+		filters
+			.lock()
+			.unwrap()
+			.push((Registration::Filter(name.to_owned()), function));
+	});
+	let tags = registrations.clone();
+	engine.register_fn("register_tag", move |name: &str, function: FnPtr| {
+		tags.lock()
+			.unwrap()
+			.push((Registration::Tag(name.to_owned()), function));
+	});
+	engine.register_fn(
+		"on",
+		move |event: &str, function: FnPtr| -> std::result::Result<(), Box<EvalAltResult>> {
+			match event {
+				BEFORE_RENDER | AFTER_RENDER | AFTER_BUILD => {
+					registrations
+						.lock()
+						.unwrap()
+						.push((Registration::Hook(event.to_owned()), function));
+					Ok(())
+				}
+				_ => Err(format!(
+					"There is no event named '{event}'; hooks may be registered for '{BEFORE_RENDER}', '{AFTER_RENDER}' or '{AFTER_BUILD}'."
+				)
+				.into()),
+			}
+		},
+	);
+	if options.read_files {
+		engine.register_fn(
+			"read_file",
+			move |path: &str| -> std::result::Result<String, Box<EvalAltResult>> {
+				// Only files within the Mokk are read
+				let file = contained_path(path).ok_or(format!(
+					"Unable to read the file at '{path}', as it is outside of the Mokk."
+				))?;
+				source
+					.read_to_string(&file)
+					.map_err(|e| format!("Unable to read the file at '{path}': {e}").into())
+			},
+		);
+	}
+	engine
+}
+
+/// Returns a Liquid value as a value of a script
+///
+/// # Arguments
+///
+/// * `value` - The Liquid value
+fn liquid_to_script(value: &dyn ValueView) -> Result<Dynamic> {
+	to_dynamic(value.to_value()).map_err(|e| Error::with_msg(e.to_string()))
+}
+
+#[derive(Clone, Debug)]
+/// A Liquid filter registered by a script, given the input & any positional arguments, such as `{{ title | shout: "!" }}`
+pub struct ScriptFilter {
+	/// The name of the filter
+	name: String,
+	/// The function implementing the filter
+	function: ScriptFunction,
+}
+
+impl FilterReflection for ScriptFilter {
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn description(&self) -> &str {
+		"A filter registered by a script."
+	}
+
+	fn positional_parameters(&self) -> &'static [ParameterReflection] {
+		&[]
+	}
+
+	fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+		&[]
+	}
+}
+
+impl ParseFilter for ScriptFilter {
+	fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+		if arguments.keyword.next().is_some() {
+			return Err(Error::with_msg(format!(
+				"The filter '{}' registered by the script at '{}' does not accept keyword arguments.",
+				self.name, self.function.path
+			)));
+		}
+		Ok(Box::new(ScriptFilterCall {
+			name: self.name.clone(),
+			arguments: arguments.positional.collect(),
+			function: self.function.clone(),
+		}))
+	}
+
+	fn reflection(&self) -> &dyn FilterReflection {
+		self
+	}
+}
+
+#[derive(Debug)]
+/// A parsed use of a filter registered by a script
+struct ScriptFilterCall {
+	name: String,
+	arguments: Vec<Expression>,
+	function: ScriptFunction,
+}
+
+impl fmt::Display for ScriptFilterCall {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.name)
+	}
+}
+
+impl Filter for ScriptFilterCall {
+	fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+		let mut arguments = vec![liquid_to_script(input)?];
+		for argument in &self.arguments {
+			arguments.push(liquid_to_script(argument.evaluate(runtime)?.as_view())?);
+		}
+		let result = self.function.call(arguments).map_err(|e| {
+			Error::with_msg(format!(
+				"Unable to run the filter '{}' registered by the script at '{}': {e}",
+				self.name, self.function.path
+			))
+		})?;
+		liquid_core::to_value(&result)
+	}
+}
+
+#[derive(Clone)]
+/// A Liquid tag registered by a script, given any arguments, such as `{% year %}` or `{% greet "world" %}`; it renders the value it returns
+pub struct ScriptTag {
+	/// The name of the tag
+	name: &'static str,
+	/// The function implementing the tag
+	function: ScriptFunction,
+}
+
+impl TagReflection for ScriptTag {
+	fn tag(&self) -> &'static str {
+		self.name
+	}
+
+	fn description(&self) -> &'static str {
+		"A tag registered by a script."
+	}
+}
+
+impl ParseTag for ScriptTag {
+	fn parse(
+		&self,
+		mut arguments: TagTokenIter<'_>,
+		_options: &Language,
+	) -> Result<Box<dyn Renderable>> {
+		let mut values = Vec::new();
+		while let Ok(next) = arguments.expect_next("") {
+			values.push(next.expect_value().into_result()?);
+		}
+		arguments.expect_nothing()?;
+
+		Ok(Box::new(ScriptTagCall {
+			name: self.name,
+			arguments: values,
+			function: self.function.clone(),
+		}))
+	}
+
+	fn reflection(&self) -> &dyn TagReflection {
+		self
+	}
+}
+
+#[derive(Debug)]
+/// A parsed use of a tag registered by a script
+struct ScriptTagCall {
+	name: &'static str,
+	arguments: Vec<Expression>,
+	function: ScriptFunction,
+}
+
+impl Renderable for ScriptTagCall {
+	fn render_to(&self, writer: &mut dyn Write, runtime: &dyn Runtime) -> Result<()> {
+		let mut arguments = Vec::new();
+		for argument in &self.arguments {
+			arguments.push(liquid_to_script(argument.evaluate(runtime)?.as_view())?);
+		}
+		let result = self
+			.function
+			.call(arguments)
+			.map_err(|e| {
+				Error::with_msg(format!(
+					"Unable to run the tag '{}' registered by the script at '{}': {e}",
+					self.name, self.function.path
+				))
+			})
+			.trace_with(|| format!("{{% {} %}}", self.name).into())?;
+		if !result.is_unit() {
+			write!(writer, "{result}").replace("Failed to render")?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,300 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+tags.rs - Liquid tags provided by Dokkoo
+
+Tags in addition to those of the Liquid standard library, available to every Mokk file.
+*/
+
+use crate::files::{contained_path, SiteSource};
+use crate::highlight::{parse_line_ranges, CodeBlockAttributes, Highlighter};
+use liquid_core::error::{ResultLiquidExt, ResultLiquidReplaceExt};
+use liquid_core::{
+	Error, Expression, Language, ParseTag, Renderable, Result, Runtime, TagReflection,
+	TagTokenIter, ValueView,
+};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// The files read while rendering, shared between the tags that read them and the build
+pub type Dependencies = Arc<Mutex<Vec<String>>>;
+
+/// Records that a file was read while rendering
+///
+/// # Arguments
+///
+/// * `source` - Where the file was read from
+///
+/// * `dependencies` - The files read while rendering
+///
+/// * `path` - The path to the file that was read
+pub fn add_dependency(source: &dyn SiteSource, dependencies: &Dependencies, path: &Path) {
+	let path = source.canonical_path(path).to_string_lossy().to_string();
+	let mut dependencies = dependencies.lock().unwrap();
+	if !dependencies.contains(&path) {
+		dependencies.push(path);
+	}
+}
+
+/// Parses the `key=value` arguments of a tag
+///
+/// # Arguments
+///
+/// * `arguments` - The remaining arguments of the tag
+fn parse_tag_arguments(mut arguments: TagTokenIter<'_>) -> Result<Vec<(String, Expression)>> {
+	let mut parsed_arguments = Vec::new();
+	while let Ok(next) = arguments.expect_next("") {
+		let id = next.expect_identifier().into_result()?.to_owned();
+
+		arguments
+			.expect_next("\"=\" expected.")?
+			.expect_str("=")
+			.into_result_custom_msg("expected \"=\" to be used for the assignment")?;
+
+		parsed_arguments.push((
+			id,
+			arguments
+				.expect_next("expected value")?
+				.expect_value()
+				.into_result()?,
+		));
+	}
+	arguments.expect_nothing()?;
+	Ok(parsed_arguments)
+}
+
+#[derive(Clone)]
+/// `{% code "examples/hello.rs" lines="10-25" lang="rust" %}`; includes highlighted code from a file.
+///
+/// The file is read relative to the Mokk, and must be within it. Either ranges of lines (`lines`, such as `3-5,10-12`), numbered as they are in the file, or a region between `ANCHOR: name` & `ANCHOR_END: name` markers (`anchor`) can be included.
+/// The language is inferred from the file extension unless given with `lang`; the `linenos`, `linenostart`, `hl_lines` & `title` attributes of code blocks are also accepted.
+pub struct CodeTag {
+	/// Where the included files are read from
+	pub source: Arc<dyn SiteSource>,
+	/// The syntax highlighter used for the included code
+	pub highlighter: Arc<Highlighter>,
+	/// The files read while rendering
+	pub dependencies: Dependencies,
+}
+
+impl TagReflection for CodeTag {
+	fn tag(&self) -> &'static str {
+		"code"
+	}
+
+	fn description(&self) -> &'static str {
+		"Includes highlighted code from a file."
+	}
+}
+
+impl ParseTag for CodeTag {
+	fn parse(
+		&self,
+		mut arguments: TagTokenIter<'_>,
+		_options: &Language,
+	) -> Result<Box<dyn Renderable>> {
+		let path = arguments
+			.expect_next("Path to a file expected.")?
+			.expect_value()
+			.into_result()?;
+
+		Ok(Box::new(Code {
+			path,
+			arguments: parse_tag_arguments(arguments)?,
+			source: self.source.clone(),
+			highlighter: self.highlighter.clone(),
+			dependencies: self.dependencies.clone(),
+		}))
+	}
+
+	fn reflection(&self) -> &dyn TagReflection {
+		self
+	}
+}
+
+/// A parsed `{% code %}` tag
+struct Code {
+	path: Expression,
+	arguments: Vec<(String, Expression)>,
+	source: Arc<dyn SiteSource>,
+	highlighter: Arc<Highlighter>,
+	dependencies: Dependencies,
+}
+
+impl std::fmt::Debug for Code {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("Code")
+			.field("path", &self.path)
+			.field("arguments", &self.arguments)
+			.finish()
+	}
+}
+
+impl Renderable for Code {
+	fn render_to(&self, writer: &mut dyn Write, runtime: &dyn Runtime) -> Result<()> {
+		let path = self.path.evaluate(runtime)?.render().to_string();
+		let mut meta = String::new();
+		let mut lines = None;
+		let mut anchor = None;
+		let mut lang = Path::new(&path)
+			.extension()
+			.map(|e| e.to_string_lossy().to_string());
+		for (key, value) in &self.arguments {
+			let value = value.evaluate(runtime)?.render().to_string();
+			match key.as_str() {
+				"lines" => {
+					lines = Some(
+						parse_line_ranges(&value)
+							.map_err(|e| Error::with_msg(e.to_string()))
+							.trace_with(|| format!("{{% code \"{path}\" %}}").into())?,
+					)
+				}
+				"anchor" => anchor = Some(value),
+				"lang" => lang = Some(value),
+				_ => meta.push_str(&format!("{key}={value:?} ")),
+			}
+		}
+
+		// Only files within the Mokk are included
+		let file = contained_path(&path)
+			.ok_or(Error::with_msg(format!(
+				"Unable to include the file at '{path}', as it is outside of the Mokk."
+			)))
+			.trace_with(|| format!("{{% code \"{path}\" %}}").into())?;
+		let text = self
+			.source
+			.read_to_string(&file)
+			.map_err(|e| Error::with_msg(format!("Unable to read the file at '{path}': {e}")))
+			.trace_with(|| format!("{{% code \"{path}\" %}}").into())?;
+		add_dependency(self.source.as_ref(), &self.dependencies, &file);
+
+		let mut code = String::new();
+		let mut first_line = 1;
+		let mut line_numbers = Vec::new();
+		match (lines, anchor) {
+			(Some(ranges), _) => {
+				// Lines are numbered as they are in the file, even when several ranges are included
+				for (index, line) in text.lines().enumerate() {
+					if ranges
+						.iter()
+						.any(|(start, end)| (*start..=*end).contains(&(index + 1)))
+					{
+						code.push_str(line);
+						code.push('\n');
+						line_numbers.push(index + 1);
+					}
+				}
+			}
+			(None, Some(anchor)) => {
+				let start_marker = format!("ANCHOR: {anchor}");
+				let end_marker = format!("ANCHOR_END: {anchor}");
+				let mut within = false;
+				let mut found = false;
+				for (index, line) in text.lines().enumerate() {
+					if line.trim_end().ends_with(&start_marker) {
+						within = true;
+						found = true;
+						first_line = index + 2;
+					} else if line.trim_end().ends_with(&end_marker) {
+						within = false;
+					} else if within && !line.contains("ANCHOR: ") && !line.contains("ANCHOR_END: ")
+					{
+						code.push_str(line);
+						code.push('\n');
+					}
+				}
+				if !found {
+					return Err(Error::with_msg(format!(
+						"Unable to find the anchor '{anchor}' in the file at '{path}'."
+					)));
+				}
+			}
+			(None, None) => {
+				for line in text.lines() {
+					if !line.contains("ANCHOR: ") && !line.contains("ANCHOR_END: ") {
+						code.push_str(line);
+						code.push('\n');
+					}
+				}
+			}
+		}
+
+		let mut attributes =
+			CodeBlockAttributes::parse(&meta, self.highlighter.options.line_numbers);
+		if !meta.contains("linenostart=") {
+			attributes.line_number_start = first_line;
+			attributes.line_number_list = line_numbers;
+		}
+		// Line breaks are written as character references so that Markdown treats the code block as a single HTML block
+		let html = self
+			.highlighter
+			.render_code_block(&code, lang.as_deref(), &attributes);
+		writeln!(writer, "{}", html.trim_end().replace('\n', "&#10;"))
+			.replace("Failed to render")?;
+		Ok(())
+	}
+}
+
+#[derive(Clone)]
+/// `{% eqref "eq:foo" %}`; refers to a labelled equation, rendering a link to it once the Mokk's math is rendered.
+pub struct EqrefTag;
+
+impl TagReflection for EqrefTag {
+	fn tag(&self) -> &'static str {
+		"eqref"
+	}
+
+	fn description(&self) -> &'static str {
+		"Refers to a labelled equation."
+	}
+}
+
+impl ParseTag for EqrefTag {
+	fn parse(
+		&self,
+		mut arguments: TagTokenIter<'_>,
+		_options: &Language,
+	) -> Result<Box<dyn Renderable>> {
+		let label = arguments
+			.expect_next("Label of an equation expected.")?
+			.expect_value()
+			.into_result()?;
+		arguments.expect_nothing()?;
+
+		Ok(Box::new(Eqref { label }))
+	}
+
+	fn reflection(&self) -> &dyn TagReflection {
+		self
+	}
+}
+
+#[derive(Debug)]
+/// A parsed `{% eqref %}` tag
+struct Eqref {
+	label: Expression,
+}
+
+impl Renderable for Eqref {
+	fn render_to(&self, writer: &mut dyn Write, runtime: &dyn Runtime) -> Result<()> {
+		let label = self.label.evaluate(runtime)?.render().to_string();
+		// The reference is resolved along with the rest of the Mokk's math
+		write!(writer, "\\eqref{{{label}}}").replace("Failed to render")?;
+		Ok(())
+	}
+}
@@ -0,0 +1,532 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+toc.rs - Headings & tables of contents
+
+The headings of a Mokk file are given stable IDs and collected while its Markdown is rendered, then arranged into a table of contents.
+*/
+
+use crate::escape_html;
+use ahash::AHashSet;
+use comrak::adapters::{HeadingAdapter, HeadingMeta};
+use comrak::nodes::{AstNode, NodeValue, Sourcepos};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+#[derive(Eq, PartialEq, Clone, Default, Debug, Serialize, Deserialize)]
+/// A heading found while rendering a Mokk file
+pub struct Heading {
+	/// The level of the heading, from 1 to 6
+	pub level: u8,
+	/// The text of the heading, without any formatting
+	pub text: String,
+	/// The ID of the heading's anchor
+	pub id: String,
+}
+
+#[derive(Eq, PartialEq, Clone, Default, Debug, Serialize, Deserialize)]
+/// An entry in a table of contents
+pub struct TocEntry {
+	/// The level of the heading, from 1 to 6
+	pub level: u8,
+	/// The text of the heading, without any formatting
+	pub text: String,
+	/// The ID of the heading's anchor
+	pub id: String,
+	/// The entries for the headings nested under this one
+	pub children: Vec<TocEntry>,
+}
+
+#[derive(Eq, PartialEq, Clone, Default, Debug, Serialize, Deserialize)]
+/// A Mokk file's table of contents
+pub struct Toc {
+	/// The top-level entries of the table of contents
+	pub entries: Vec<TocEntry>,
+	/// The table of contents, as nested HTML lists
+	pub html: String,
+}
+
+impl Toc {
+	/// Arranges headings into a table of contents
+	///
+	/// # Arguments
+	///
+	/// * `headings` - The headings of a Mokk file, in the order they appear
+	///
+	/// * `min_level` - The level of the highest headings to include
+	///
+	/// * `max_level` - The level of the lowest headings to include
+	pub fn new(headings: &[Heading], min_level: u8, max_level: u8) -> Self {
+		let mut entries: Vec<TocEntry> = Vec::new();
+		for heading in headings
+			.iter()
+			.filter(|h| h.level >= min_level && h.level <= max_level)
+		{
+			let entry = TocEntry {
+				level: heading.level,
+				text: heading.text.clone(),
+				id: heading.id.clone(),
+				children: Vec::new(),
+			};
+			// Descend to the last entry of a higher level than this heading, and nest it there
+			let mut siblings = &mut entries;
+			while siblings
+				.last()
+				.map(|last| last.level < entry.level)
+				.unwrap_or(false)
+			{
+				siblings = &mut siblings.last_mut().unwrap().children;
+			}
+			siblings.push(entry);
+		}
+
+		let mut html = String::new();
+		write_toc_list(&mut html, &entries);
+		Self { entries, html }
+	}
+}
+
+/// Writes entries of a table of contents as a nested HTML list
+///
+/// # Arguments
+///
+/// * `html` - Where to write the list
+///
+/// * `entries` - The entries of the list
+fn write_toc_list(html: &mut String, entries: &[TocEntry]) {
+	if entries.is_empty() {
+		return;
+	}
+	html.push_str("<ul class=\"toc\">");
+	for entry in entries {
+		write!(
+			html,
+			"<li><a href=\"#{}\">{}</a>",
+			escape_html(&entry.id),
+			escape_html(&entry.text)
+		)
+		.unwrap();
+		write_toc_list(html, &entry.children);
+		html.push_str("</li>");
+	}
+	html.push_str("</ul>");
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+/// Configuration of heading IDs & anchors, from the `headings` key of the global file
+pub struct HeadingOptions {
+	/// The prefix of each heading's ID
+	pub prefix: String,
+	/// Whether non-ASCII characters in headings are transliterated into ASCII when generating IDs
+	pub transliterate: bool,
+	/// Whether IDs are made lowercase
+	pub lowercase: bool,
+	/// The text replacing whitespace in IDs
+	pub separator: String,
+	/// Whether a link to each heading is inserted after its text
+	pub anchors: bool,
+	/// The text of the links inserted after headings
+	pub anchor_symbol: String,
+	/// The CSS class of the links inserted after headings
+	pub anchor_class: String,
+}
+
+/// The initial state of a `HeadingOptions` object
+impl Default for HeadingOptions {
+	fn default() -> Self {
+		Self {
+			prefix: "h-".to_owned(),
+			transliterate: false,
+			lowercase: true,
+			separator: "-".to_owned(),
+			anchors: false,
+			anchor_symbol: "¶".to_owned(),
+			anchor_class: "anchor".to_owned(),
+		}
+	}
+}
+
+impl HeadingOptions {
+	/// Returns the slug of a heading's text, not including the prefix
+	///
+	/// # Arguments
+	///
+	/// * `text` - The text of the heading
+	pub fn slugify(&self, text: &str) -> String {
+		let text = match self.transliterate {
+			true => deunicode::deunicode(text),
+			false => text.to_owned(),
+		};
+		let text = match self.lowercase {
+			true => text.to_lowercase(),
+			false => text,
+		};
+		text.split_whitespace()
+			.map(|word| {
+				word.chars()
+					.filter(|c| c.is_alphanumeric() || matches!(c, '-' | '_'))
+					.collect::<String>()
+			})
+			.filter(|word| !word.is_empty())
+			.collect::<Vec<String>>()
+			.join(&self.separator)
+	}
+}
+
+/// Returns a heading's text without its explicit ID, along with the ID, if the text ends with one (e.g. `Installation {#install}`)
+///
+/// # Arguments
+///
+/// * `text` - The text of the heading
+pub fn split_custom_id(text: &str) -> Option<(&str, &str)> {
+	let trimmed = text.trim_end();
+	let start = trimmed.strip_suffix('}')?.rfind("{#")?;
+	let id = &trimmed[start + 2..trimmed.len() - 1];
+	match !id.is_empty() && !id.contains(char::is_whitespace) {
+		true => Some((&trimmed[..start], id)),
+		false => None,
+	}
+}
+
+/// Returns the values of the `id` attributes of the elements in HTML
+///
+/// # Arguments
+///
+/// * `html` - The HTML
+pub fn html_ids(html: &str) -> Vec<String> {
+	html.split(" id=\"")
+		.skip(1)
+		.filter_map(|rest| rest.split_once('"'))
+		.map(|(id, _)| id.to_owned())
+		.collect()
+}
+
+/// Gives each heading with an explicit ID (e.g. `## Installation {#install}`) that ID, removing it from the heading's text
+///
+/// # Arguments
+///
+/// * `root` - The root node of a Markdown document
+///
+/// * `heading_collector` - The collector which will render the document's headings
+pub fn apply_custom_ids<'a>(root: &'a AstNode<'a>, heading_collector: &HeadingCollector) {
+	for node in root.descendants() {
+		if !matches!(node.data.borrow().value, NodeValue::Heading(_)) {
+			continue;
+		}
+		let mut custom_id = None;
+		if let Some(last_child) = node.last_child() {
+			if let NodeValue::Text(ref mut text) = last_child.data.borrow_mut().value {
+				if let Some((stripped, id)) = split_custom_id(text) {
+					custom_id = Some(id.to_owned());
+					*text = stripped.trim_end().to_owned();
+				}
+			}
+		}
+		heading_collector
+			.custom_ids
+			.lock()
+			.unwrap()
+			.push_back(custom_id);
+	}
+}
+
+/// Renders the headings of Markdown documents, collecting them as they are rendered.
+/// IDs are kept unique across every document rendered until the collector is cleared, such as a page & its layouts.
+pub struct HeadingCollector {
+	/// The configuration of heading IDs & anchors
+	pub options: HeadingOptions,
+	/// The IDs given out so far
+	used_ids: Mutex<AHashSet<String>>,
+	/// The headings rendered so far
+	headings: Mutex<Vec<Heading>>,
+	/// The explicit IDs of the headings yet to be rendered, in order
+	custom_ids: Mutex<VecDeque<Option<String>>>,
+	/// The ID of the heading being rendered
+	current_id: Mutex<String>,
+}
+
+/// The initial state of a `HeadingCollector` object
+impl Default for HeadingCollector {
+	fn default() -> Self {
+		Self::new(HeadingOptions::default())
+	}
+}
+
+impl HeadingCollector {
+	/// Creates a collector with no headings
+	///
+	/// # Arguments
+	///
+	/// * `options` - The configuration of heading IDs & anchors
+	pub fn new(options: HeadingOptions) -> Self {
+		Self {
+			options,
+			used_ids: Mutex::new(AHashSet::new()),
+			headings: Mutex::new(Vec::new()),
+			custom_ids: Mutex::new(VecDeque::new()),
+			current_id: Mutex::new(String::new()),
+		}
+	}
+
+	/// Forgets the headings & IDs collected so far
+	pub fn clear(&self) {
+		self.used_ids.lock().unwrap().clear();
+		self.headings.lock().unwrap().clear();
+		self.custom_ids.lock().unwrap().clear();
+	}
+
+	/// Returns the IDs of the headings rendered so far which are given to more than one element of an HTML document, such as a page within its layouts
+	///
+	/// # Arguments
+	///
+	/// * `html` - The HTML containing the headings
+	pub fn duplicate_ids(&self, html: &str) -> Vec<String> {
+		self.headings
+			.lock()
+			.unwrap()
+			.iter()
+			.map(|heading| heading.id.clone())
+			.filter(|id| {
+				html.matches(&format!(" id=\"{}\"", escape_html(id)))
+					.count()
+					> 1
+			})
+			.collect()
+	}
+
+	/// Gives new IDs to the headings rendered so far whose IDs are given to other elements of an HTML document, such as a page within its layouts.
+	/// Returns the HTML the headings were rendered into, with the headings & the links to them changed, or `None` if no heading needs a new ID.
+	///
+	/// # Arguments
+	///
+	/// * `html` - The HTML containing the headings
+	///
+	/// * `document` - The HTML the headings were rendered into, such as the contents of the page
+	pub fn rename_duplicate_ids(&self, html: &str, document: &str) -> Option<String> {
+		let duplicate_ids = self.duplicate_ids(html);
+		if duplicate_ids.is_empty() {
+			return None;
+		}
+		// New IDs are given to no other element
+		self.used_ids.lock().unwrap().extend(html_ids(html));
+
+		let mut document = document.to_owned();
+		for heading in self
+			.headings
+			.lock()
+			.unwrap()
+			.iter_mut()
+			.filter(|heading| duplicate_ids.contains(&heading.id))
+		{
+			let id = self.unique_id(heading.id.clone());
+			document = document
+				.replacen(
+					&format!("<h{} id=\"{}\">", heading.level, escape_html(&heading.id)),
+					&format!("<h{} id=\"{}\">", heading.level, escape_html(&id)),
+					1,
+				)
+				.replace(
+					&format!("href=\"#{}\"", escape_html(&heading.id)),
+					&format!("href=\"#{}\"", escape_html(&id)),
+				);
+			heading.id = id;
+		}
+		Some(document)
+	}
+
+	/// Returns the headings rendered so far
+	pub fn headings(&self) -> Vec<Heading> {
+		self.headings.lock().unwrap().clone()
+	}
+
+	/// Replaces the text of the headings rendered so far
+	///
+	/// # Arguments
+	///
+	/// * `f` - Returns the new text of a heading, given its current text
+	pub fn map_text(&self, f: impl Fn(&str) -> String) {
+		for heading in self.headings.lock().unwrap().iter_mut() {
+			heading.text = f(&heading.text);
+		}
+	}
+
+	/// Returns an ID not yet given to another heading, appending a number to the given ID if needed
+	///
+	/// # Arguments
+	///
+	/// * `id` - The preferred ID
+	pub fn unique_id(&self, id: String) -> String {
+		let mut used_ids = self.used_ids.lock().unwrap();
+		let mut unique_id = id.clone();
+		let mut suffix = 0;
+		while used_ids.contains(&unique_id) {
+			suffix += 1;
+			unique_id = format!("{id}{}{suffix}", self.options.separator);
+		}
+		used_ids.insert(unique_id.clone());
+		unique_id
+	}
+
+	/// Returns the link inserted after a heading's text, if anchors are enabled
+	///
+	/// # Arguments
+	///
+	/// * `id` - The ID of the heading
+	pub fn anchor_html(&self, id: &str) -> String {
+		match self.options.anchors {
+			true => format!(
+				" <a href=\"#{}\" class=\"{}\" aria-hidden=\"true\">{}</a>",
+				escape_html(id),
+				escape_html(&self.options.anchor_class),
+				escape_html(&self.options.anchor_symbol)
+			),
+			false => String::new(),
+		}
+	}
+
+	/// Renders a heading written in a format other than Markdown, collecting it
+	///
+	/// # Arguments
+	///
+	/// * `level` - The level of the heading, from 1 to 6
+	///
+	/// * `text` - The text of the heading, without any formatting
+	///
+	/// * `html` - The contents of the heading, as HTML
+	pub fn render_heading(&self, level: u8, text: &str, html: &str) -> String {
+		let id = self.unique_id(format!(
+			"{}{}",
+			self.options.prefix,
+			self.options.slugify(text)
+		));
+		self.headings.lock().unwrap().push(Heading {
+			level,
+			text: text.to_owned(),
+			id: id.clone(),
+		});
+		format!(
+			"<h{level} id=\"{}\">{html}{}</h{level}>\n",
+			escape_html(&id),
+			self.anchor_html(&id)
+		)
+	}
+}
+
+impl HeadingAdapter for HeadingCollector {
+	fn enter(
+		&self,
+		output: &mut dyn Write,
+		heading: &HeadingMeta,
+		_sourcepos: Option<Sourcepos>,
+	) -> io::Result<()> {
+		let id = match self.custom_ids.lock().unwrap().pop_front().flatten() {
+			Some(custom_id) => self.unique_id(custom_id),
+			None => self.unique_id(format!(
+				"{}{}",
+				self.options.prefix,
+				self.options.slugify(&heading.content)
+			)),
+		};
+		write!(output, "<h{} id=\"{}\">", heading.level, escape_html(&id))?;
+		self.headings.lock().unwrap().push(Heading {
+			level: heading.level,
+			text: heading.content.clone(),
+			id: id.clone(),
+		});
+		*self.current_id.lock().unwrap() = id;
+		Ok(())
+	}
+
+	fn exit(&self, output: &mut dyn Write, heading: &HeadingMeta) -> io::Result<()> {
+		write!(
+			output,
+			"{}",
+			self.anchor_html(&self.current_id.lock().unwrap())
+		)?;
+		writeln!(output, "</h{}>", heading.level)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn splits_custom_ids() {
+		assert_eq!(
+			split_custom_id("Installation {#install}"),
+			Some(("Installation ", "install"))
+		);
+		assert_eq!(
+			split_custom_id("Installation {#install}  "),
+			Some(("Installation ", "install"))
+		);
+		assert_eq!(split_custom_id("Installation {#}"), None);
+		assert_eq!(split_custom_id("Installation {#in stall}"), None);
+		assert_eq!(split_custom_id("Installation"), None);
+	}
+
+	#[test]
+	fn gives_unique_ids() {
+		let collector = HeadingCollector::new(HeadingOptions::default());
+		assert_eq!(collector.unique_id("h-intro".to_owned()), "h-intro");
+		assert_eq!(collector.unique_id("h-intro".to_owned()), "h-intro-1");
+		assert_eq!(collector.unique_id("h-intro".to_owned()), "h-intro-2");
+		collector.clear();
+		assert_eq!(collector.unique_id("h-intro".to_owned()), "h-intro");
+	}
+
+	#[test]
+	fn renames_ids_used_elsewhere() {
+		let collector = HeadingCollector::new(HeadingOptions::default());
+		let content = format!(
+			"{}{}<a href=\"#h-intro\">Intro</a>",
+			collector.render_heading(1, "Intro", "Intro"),
+			collector.render_heading(1, "Intro", "Intro")
+		);
+		let html = format!("<nav id=\"h-intro\"></nav><span id=\"h-intro-2\"></span>{content}");
+		assert_eq!(collector.duplicate_ids(&html), vec!["h-intro".to_owned()]);
+
+		let content = collector.rename_duplicate_ids(&html, &content).unwrap();
+		assert_eq!(
+			content,
+			"<h1 id=\"h-intro-3\">Intro</h1>\n<h1 id=\"h-intro-1\">Intro</h1>\n<a href=\"#h-intro-3\">Intro</a>"
+		);
+		assert_eq!(
+			collector
+				.headings()
+				.into_iter()
+				.map(|heading| heading.id)
+				.collect::<Vec<_>>(),
+			vec!["h-intro-3".to_owned(), "h-intro-1".to_owned()]
+		);
+		assert_eq!(collector.rename_duplicate_ids(&content, &content), None);
+	}
+
+	#[test]
+	fn slugifies_headings() {
+		let mut options = HeadingOptions::default();
+		assert_eq!(options.slugify("Some Heading!"), "some-heading");
+		assert_eq!(options.slugify("Café  au lait"), "café-au-lait");
+		options.transliterate = true;
+		assert_eq!(options.slugify("Café  au lait"), "cafe-au-lait");
+	}
+}
@@ -0,0 +1,117 @@
+/*
+	This file is part of Dokkoo.
+
+	Dokkoo is free software: you can redistribute it and/or modify
+	it under the terms of the GNU Affero General Public License as published by
+	the Free Software Foundation, either version 3 of the License, or
+	(at your option) any later version.
+
+	Dokkoo is distributed in the hope that it will be useful,
+	but WITHOUT ANY WARRANTY; without even the implied warranty of
+	MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+	GNU Affero General Public License for more details.
+
+	You should have received a copy of the GNU Affero General Public License
+	along with Dokkoo.  If not, see <https://www.gnu.org/licenses/>.
+*/
+/*
+wasm.rs - The JavaScript API of Dokkoo
+
+When compiled to WebAssembly with the `wasm` feature (and without the default features), Dokkoo can preview pages in the browser, such as in an editor.
+As there are no files to read, pages are previewed without their layouts, snippets or the Mokk's global file.
+Errors cannot be caught in WebAssembly; they abort the call, and their messages are written to the browser's console.
+*/
+
+use crate::{math, render_markdown, Build};
+use miette::{IntoDiagnostic, WrapErr};
+use std::panic;
+use std::sync::Once;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+	/// Writes an error to the browser's console
+	#[wasm_bindgen(js_namespace = console, js_name = error)]
+	fn console_error(message: &str);
+}
+
+/// Ensures errors are sent to the browser's console by installing a panic hook, once
+static PANIC_HOOK: Once = Once::new();
+
+/// Renders previews of pages
+#[wasm_bindgen]
+pub struct Preview {
+	/// The build rendering the previews
+	build: Build,
+}
+
+#[wasm_bindgen]
+impl Preview {
+	/// Creates a previewer, with the default global configuration
+	#[wasm_bindgen(constructor)]
+	#[allow(clippy::new_without_default)]
+	pub fn new() -> Preview {
+		PANIC_HOOK.call_once(|| panic::set_hook(Box::new(|info| console_error(&info.to_string()))));
+		Preview {
+			build: Build::default(),
+		}
+	}
+
+	/// Renders the contents of a Mokk file as HTML, ignoring its layout & collection
+	///
+	/// # Arguments
+	///
+	/// * `path` - The path of the Mokk file, which determines its format if not given in its frontmatter
+	///
+	/// * `text` - The text of the Mokk file, including its frontmatter
+	pub fn render(&mut self, path: &str, text: &str) -> String {
+		let mut page = self
+			.build
+			.get_page_object_from_text(path.to_owned(), text.to_owned());
+		// Layouts cannot be read, and previews are not entries of collections
+		page.data.remove("layout");
+		page.data.remove("collection");
+		self.build.compile(page)
+	}
+
+	/// Renders Markdown as HTML, without Liquid
+	///
+	/// # Arguments
+	///
+	/// * `text` - The Markdown to render
+	///
+	/// * `math` - Whether or not to render LaTeX math as MathML
+	#[wasm_bindgen(js_name = renderMarkdown)]
+	pub fn render_markdown(&self, text: &str, math: bool) -> String {
+		render_markdown(text.to_owned(), math, &self.build.highlighter)
+	}
+
+	/// Returns the stylesheet for highlighted code, if code is highlighted with classes
+	pub fn stylesheet(&self) -> Option<String> {
+		self.build.highlighter.stylesheet()
+	}
+}
+
+/// Returns a Mokk file's frontmatter & contents, in that order
+///
+/// # Arguments
+///
+/// * `text` - The text of the Mokk file
+#[wasm_bindgen(js_name = splitFrontmatter)]
+pub fn split_frontmatter(text: &str) -> Vec<String> {
+	let (frontmatter, contents) = crate::split_frontmatter(text.to_owned());
+	vec![frontmatter, contents]
+}
+
+/// Renders the LaTeX math in HTML as MathML
+///
+/// # Arguments
+///
+/// * `html` - The HTML containing LaTeX math
+#[wasm_bindgen(js_name = renderMath)]
+pub fn render_math(html: &str) -> String {
+	math::render_math(html)
+		.into_diagnostic()
+		.wrap_err(format!("Unable to render math in document ('{html}')."))
+		.unwrap()
+}